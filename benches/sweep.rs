@@ -0,0 +1,64 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use tagged_interval::workload::Workload;
+
+fn history_size(c: &mut Criterion) {
+    let mut group = c.benchmark_group("history size");
+    for entries in [100, 1_000, 10_000] {
+        let workload = Workload {
+            entries,
+            ..Workload::default()
+        };
+        let (specified, history) = (workload.specified(), workload.history());
+        group.bench_with_input(
+            BenchmarkId::from_parameter(entries),
+            &history,
+            |b, history| b.iter(|| specified.clone().difference(history.clone())),
+        );
+    }
+    group.finish();
+}
+
+fn overlap(c: &mut Criterion) {
+    let mut group = c.benchmark_group("overlap");
+    for overlap in [0.5, 2.0, 8.0] {
+        let workload = Workload {
+            overlap,
+            ..Workload::default()
+        };
+        let (specified, history) = (workload.specified(), workload.history());
+        group.bench_with_input(
+            BenchmarkId::from_parameter(overlap),
+            &history,
+            |b, history| b.iter(|| specified.clone().difference(history.clone())),
+        );
+    }
+    group.finish();
+}
+
+fn tag_cardinality(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tag cardinality");
+    // Across the u64, u128 and HashSet tag sets of the sweep.
+    for tags in [8, 100, 500] {
+        let workload = Workload {
+            tags,
+            tags_per_entry: 4,
+            ..Workload::default()
+        };
+        let (specified, history) = (workload.specified(), workload.history());
+        group.bench_with_input(BenchmarkId::from_parameter(tags), &history, |b, history| {
+            b.iter(|| specified.clone().difference(history.clone()))
+        });
+    }
+    group.finish();
+}
+
+fn zigzag(c: &mut Criterion) {
+    let workload = Workload::default();
+    let (specified, history) = (workload.specified(), workload.zigzag());
+    c.bench_function("zigzag", |b| {
+        b.iter(|| specified.clone().difference(history.clone()))
+    });
+}
+
+criterion_group!(benches, history_size, overlap, tag_cardinality, zigzag);
+criterion_main!(benches);
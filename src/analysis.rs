@@ -0,0 +1,562 @@
+use crate::timeline::Timeline;
+use crate::{flatten_per_tag, BoundOps, Length, TaggedInterval};
+use std::collections::{HashMap, HashSet};
+use std::mem::size_of;
+use std::ops::{Add, Sub};
+
+fn total_length<Bound, D>(timeline: &Timeline<Bound>) -> D
+where
+    Bound: BoundOps + Sub<Output = D>,
+    D: Add<Output = D> + Default,
+{
+    timeline
+        .spans()
+        .into_iter()
+        .fold(D::default(), |total, (l, u)| total + (u - l))
+}
+
+/// Returns, for every pair of distinct tags in `history`, how long both are
+/// covered at the same time. Pairs are keyed in lexicographic order.
+pub fn co_occurrence<Bound, M, D>(
+    history: &[TaggedInterval<Bound, M>],
+) -> HashMap<(String, String), D>
+where
+    Bound: BoundOps + Sub<Output = D>,
+    D: Add<Output = D> + Default,
+{
+    let mut timelines: Vec<(String, Timeline<Bound>)> =
+        Timeline::per_tag(history).into_iter().collect();
+    timelines.sort_by(|x, y| x.0.cmp(&y.0));
+
+    let mut result = HashMap::new();
+    for (i, (a, timeline_a)) in timelines.iter().enumerate() {
+        for (b, timeline_b) in timelines.iter().skip(i + 1) {
+            let both = timeline_a.intersection(timeline_b);
+            result.insert((a.clone(), b.clone()), total_length(&both));
+        }
+    }
+    result
+}
+
+/// Quotes `field` for CSV as RFC 4180 requires when it holds a comma, a
+/// quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoverageMatrix<Bound>
+where
+    Bound: BoundOps,
+{
+    pub tags: Vec<String>,
+    pub buckets: Vec<(Bound, Bound)>,
+    /// Covered fraction of each bucket, indexed by tag and then by bucket.
+    pub fractions: Vec<Vec<f64>>,
+}
+
+impl<Bound> CoverageMatrix<Bound>
+where
+    Bound: BoundOps,
+{
+    /// Renders the matrix as CSV with one row per tag and one column per
+    /// bucket, headed by the bucket's lower bound. Fields are quoted as
+    /// RFC 4180 requires.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("tag");
+        for (lower, _) in self.buckets.iter() {
+            csv.push(',');
+            csv.push_str(&csv_field(&format!("{:?}", lower)));
+        }
+        csv.push('\n');
+        for (tag, fractions) in self.tags.iter().zip(self.fractions.iter()) {
+            csv.push_str(&csv_field(tag));
+            for fraction in fractions {
+                csv.push_str(&format!(",{}", fraction));
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Splits `window` into buckets of length `bucket` (the last one may be
+/// shorter) and returns how much of each bucket `history` covers for each of
+/// the window's tags.
+///
+/// Panics if `bucket` is not positive.
+pub fn coverage_matrix<Bound, M, D>(
+    window: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+    bucket: D,
+) -> CoverageMatrix<Bound>
+where
+    Bound: BoundOps + Sub<Output = D> + Add<D, Output = Bound>,
+    D: Length,
+{
+    assert!(bucket > D::default(), "bucket must be positive");
+
+    let mut buckets = vec![];
+    let mut lower = window.lower.clone();
+    while lower < window.upper {
+        let upper = (lower.clone() + bucket).min(window.upper.clone());
+        buckets.push((lower, upper.clone()));
+        lower = upper;
+    }
+
+    let mut tags: Vec<String> = window.tags.iter().cloned().collect();
+    tags.sort();
+
+    let spans = flatten_per_tag(history);
+    let fractions = tags
+        .iter()
+        .map(|tag| {
+            let spans = spans.get(tag).map(|s| s.as_slice()).unwrap_or(&[]);
+            let mut first = 0;
+            buckets
+                .iter()
+                .map(|(lower, upper)| {
+                    while first < spans.len() && spans[first].1 <= *lower {
+                        first += 1;
+                    }
+                    let covered = spans[first..].iter().take_while(|(l, _)| l < upper).fold(
+                        D::default(),
+                        |total, (l, u)| {
+                            total + (u.clone().min(upper.clone()) - l.clone().max(lower.clone()))
+                        },
+                    );
+                    covered.as_f64() / (upper.clone() - lower.clone()).as_f64()
+                })
+                .collect()
+        })
+        .collect();
+
+    CoverageMatrix {
+        tags,
+        buckets,
+        fractions,
+    }
+}
+
+/// Returns, for each of `tags`, the covered fraction of the trailing
+/// `window_length` at the points `from`, `from + step`, ... up to `to`.
+/// Each point costs a binary search per tag rather than a full difference.
+///
+/// Panics if `window_length` or `step` is not positive.
+pub fn trailing_coverage<Bound, M, D>(
+    history: &[TaggedInterval<Bound, M>],
+    (from, to): (Bound, Bound),
+    window_length: D,
+    step: D,
+    tags: &HashSet<String>,
+) -> HashMap<String, Vec<(Bound, f64)>>
+where
+    Bound: BoundOps + Sub<Output = D> + Add<D, Output = Bound> + Sub<D, Output = Bound>,
+    D: Length,
+{
+    assert!(
+        window_length > D::default(),
+        "window length must be positive"
+    );
+    assert!(step > D::default(), "step must be positive");
+
+    let mut spans = flatten_per_tag(history);
+    tags.iter()
+        .map(|tag| {
+            let spans = spans.remove(tag).unwrap_or_default();
+            let mut before = vec![0.0];
+            for (l, u) in spans.iter() {
+                before.push(before[before.len() - 1] + (u.clone() - l.clone()).as_f64());
+            }
+            let covered_until = |t: &Bound| {
+                let k = spans.partition_point(|(l, _)| l < t);
+                if k == 0 {
+                    return 0.0;
+                }
+                let (l, u) = &spans[k - 1];
+                before[k - 1] + (u.clone().min(t.clone()) - l.clone()).as_f64()
+            };
+
+            let mut series = vec![];
+            let mut point = from.clone();
+            while point <= to {
+                let start = point.clone() - window_length;
+                let covered = covered_until(&point) - covered_until(&start);
+                series.push((point.clone(), covered / window_length.as_f64()));
+                point = point + step;
+            }
+            (tag.clone(), series)
+        })
+        .collect()
+}
+
+/// Returns, for each of `tags`, how many gaps start within the trailing
+/// `window_length` at the points `from`, `from + step`, ... up to `to`,
+/// i.e. how often its coverage broke off there.
+///
+/// Panics if `window_length` or `step` is not positive.
+pub fn gap_rate<Bound, M, D>(
+    history: &[TaggedInterval<Bound, M>],
+    (from, to): (Bound, Bound),
+    window_length: D,
+    step: D,
+    tags: &HashSet<String>,
+) -> HashMap<String, Vec<(Bound, f64)>>
+where
+    Bound: BoundOps + Add<D, Output = Bound> + Sub<D, Output = Bound>,
+    D: Length,
+{
+    assert!(
+        window_length > D::default(),
+        "window length must be positive"
+    );
+    assert!(step > D::default(), "step must be positive");
+
+    let mut spans = flatten_per_tag(history);
+    tags.iter()
+        .map(|tag| {
+            let ends: Vec<Bound> = spans
+                .remove(tag)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(_, u)| u)
+                .collect();
+            let mut series = vec![];
+            let mut point = from.clone();
+            while point <= to {
+                let start = point.clone() - window_length;
+                let gaps =
+                    ends.partition_point(|u| *u < point) - ends.partition_point(|u| *u < start);
+                series.push((point.clone(), gaps as f64));
+                point = point + step;
+            }
+            (tag.clone(), series)
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnomalyKind {
+    /// Coverage fell more than the allowed drop below the recent mean.
+    Drop { baseline: f64, ratio: f64 },
+    /// Coverage reached zero after being positive.
+    Stopped,
+    /// The gap rate rose more than the allowed rise above the recent mean.
+    Spike { baseline: f64, rate: f64 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Anomaly<Bound> {
+    pub tag: String,
+    pub at: Bound,
+    pub kind: AnomalyKind,
+}
+
+/// Flags sudden changes in coverage series such as those of
+/// `trailing_coverage`, and in gap-rate series such as those of `gap_rate`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Detector {
+    /// Number of preceding points the mean is taken over.
+    pub baseline: usize,
+    /// Largest tolerated fall of coverage below that mean.
+    pub max_drop: f64,
+    /// Largest tolerated rise of the gap rate above that mean.
+    pub max_rise: f64,
+}
+
+impl Detector {
+    /// Returns the anomalies in the coverage `series`, ordered by tag and
+    /// then by point.
+    pub fn detect<Bound>(&self, series: &HashMap<String, Vec<(Bound, f64)>>) -> Vec<Anomaly<Bound>>
+    where
+        Bound: BoundOps,
+    {
+        self.scan(series, |points, i, baseline| {
+            let ratio = points[i].1;
+            if ratio == 0.0 && points[i - 1].1 > 0.0 {
+                Some(AnomalyKind::Stopped)
+            } else if baseline - ratio > self.max_drop {
+                Some(AnomalyKind::Drop { baseline, ratio })
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the spikes in the gap-rate `series`, ordered by tag and then
+    /// by point.
+    pub fn detect_spikes<Bound>(
+        &self,
+        series: &HashMap<String, Vec<(Bound, f64)>>,
+    ) -> Vec<Anomaly<Bound>>
+    where
+        Bound: BoundOps,
+    {
+        self.scan(series, |points, i, baseline| {
+            let rate = points[i].1;
+            if rate - baseline > self.max_rise {
+                Some(AnomalyKind::Spike { baseline, rate })
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Calls `classify` with each point after the first and the mean of
+    /// the `baseline` points before it.
+    fn scan<Bound, F>(
+        &self,
+        series: &HashMap<String, Vec<(Bound, f64)>>,
+        classify: F,
+    ) -> Vec<Anomaly<Bound>>
+    where
+        Bound: BoundOps,
+        F: Fn(&[(Bound, f64)], usize, f64) -> Option<AnomalyKind>,
+    {
+        let mut tags: Vec<&String> = series.keys().collect();
+        tags.sort();
+
+        let mut anomalies = vec![];
+        for tag in tags {
+            let points = &series[tag];
+            for i in 1..points.len() {
+                let recent = &points[i.saturating_sub(self.baseline.max(1))..i];
+                let baseline = recent.iter().fold(0.0, |sum, (_, r)| sum + r) / recent.len() as f64;
+                if let Some(kind) = classify(points, i, baseline) {
+                    anomalies.push(Anomaly {
+                        tag: tag.clone(),
+                        at: points[i].0.clone(),
+                        kind,
+                    });
+                }
+            }
+        }
+        anomalies
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HistoryStats {
+    pub entries: usize,
+    pub distinct_tags: usize,
+    /// Rough heap footprint of the entries and their tag sets.
+    pub estimated_bytes: usize,
+    /// Number of (entry, tag) pairs.
+    pub tag_entries: usize,
+    /// Number of (entry, tag) pairs left after coalescing each tag's
+    /// overlapping and touching entries.
+    pub minimal_tag_entries: usize,
+}
+
+impl HistoryStats {
+    /// Returns how many times more tag entries there are than needed; 1.0
+    /// for a fully coalesced history.
+    pub fn fragmentation(&self) -> f64 {
+        if self.minimal_tag_entries == 0 {
+            1.0
+        } else {
+            self.tag_entries as f64 / self.minimal_tag_entries as f64
+        }
+    }
+}
+
+/// Returns size statistics of `history`.
+pub fn history_stats<Bound, M>(history: &[TaggedInterval<Bound, M>]) -> HistoryStats
+where
+    Bound: BoundOps,
+{
+    let distinct: HashSet<&String> = history.iter().flat_map(|iv| iv.tags.iter()).collect();
+    let estimated_bytes = history
+        .iter()
+        .map(|iv| {
+            size_of::<TaggedInterval<Bound, M>>()
+                + iv.tags.capacity() * (size_of::<String>() + 1)
+                + iv.tags.iter().map(|t| t.capacity()).sum::<usize>()
+        })
+        .sum();
+
+    HistoryStats {
+        entries: history.len(),
+        distinct_tags: distinct.len(),
+        estimated_bytes,
+        tag_entries: history.iter().map(|iv| iv.tags.len()).sum(),
+        minimal_tag_entries: flatten_per_tag(history).values().map(|s| s.len()).sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiv;
+
+    #[test]
+    fn co_occurrence_works() {
+        let history = vec![
+            tiv(0i64, 10, &["freedom", "liberty"]),
+            tiv(5, 20, &["fairness"]),
+            tiv(15, 30, &["liberty"]),
+        ];
+        let expected: HashMap<(String, String), i64> = vec![
+            (("fairness", "freedom"), 5),
+            (("fairness", "liberty"), 10),
+            (("freedom", "liberty"), 10),
+        ]
+        .into_iter()
+        .map(|((a, b), n)| ((a.to_string(), b.to_string()), n))
+        .collect();
+
+        assert_eq!(co_occurrence(&history), expected);
+    }
+
+    #[test]
+    fn coverage_matrix_works() {
+        let window = tiv(0, 25, &["freedom", "liberty"]);
+        let history = vec![
+            tiv(0, 5, &["freedom", "liberty"]),
+            tiv(8, 18, &["freedom"]),
+            tiv(20, 30, &["liberty"]),
+        ];
+        let matrix = coverage_matrix(&window, &history, 10);
+
+        assert_eq!(matrix.tags, vec!["freedom", "liberty"]);
+        assert_eq!(matrix.buckets, vec![(0, 10), (10, 20), (20, 25)]);
+        assert_eq!(
+            matrix.fractions,
+            vec![vec![0.7, 0.8, 0.0], vec![0.5, 0.0, 1.0]]
+        );
+        assert_eq!(
+            matrix.to_csv(),
+            "tag,0,10,20\nfreedom,0.7,0.8,0\nliberty,0.5,0,1\n"
+        );
+
+        let window = tiv(0, 10, &["{instance=\"a\",job=\"api\"}"]);
+        let history = vec![tiv(0, 5, &["{instance=\"a\",job=\"api\"}"])];
+        assert_eq!(
+            coverage_matrix(&window, &history, 10).to_csv(),
+            "tag,0\n\"{instance=\"\"a\"\",job=\"\"api\"\"}\",0.5\n"
+        );
+    }
+
+    #[test]
+    fn history_stats_works() {
+        let history = vec![
+            tiv(0, 10, &["freedom", "liberty"]),
+            tiv(10, 20, &["freedom"]),
+            tiv(30, 40, &["freedom"]),
+        ];
+        let stats = history_stats(&history);
+
+        assert_eq!(stats.entries, 3);
+        assert_eq!(stats.distinct_tags, 2);
+        assert_eq!(stats.tag_entries, 4);
+        assert_eq!(stats.minimal_tag_entries, 3);
+        assert!(stats.estimated_bytes >= 3 * size_of::<TaggedInterval<i64>>());
+        assert_eq!(history_stats::<i64, ()>(&[]).fragmentation(), 1.0);
+    }
+
+    #[test]
+    fn trailing_coverage_works() {
+        let history = vec![
+            tiv(0, 10, &["freedom"]),
+            tiv(15, 25, &["freedom"]),
+            tiv(20, 30, &["freedom"]),
+        ];
+        let tags: HashSet<String> = ["freedom", "liberty"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let series = trailing_coverage(&history, (10, 40), 10, 10, &tags);
+
+        assert_eq!(
+            series["freedom"],
+            vec![(10, 1.0), (20, 0.5), (30, 1.0), (40, 0.0)]
+        );
+        assert_eq!(
+            series["liberty"],
+            vec![(10, 0.0), (20, 0.0), (30, 0.0), (40, 0.0)]
+        );
+    }
+
+    #[test]
+    fn detector_works() {
+        let series: HashMap<String, Vec<(i64, f64)>> = vec![
+            (
+                "freedom".to_string(),
+                vec![(0, 1.0), (1, 1.0), (2, 0.5), (3, 0.5), (4, 0.0)],
+            ),
+            ("liberty".to_string(), vec![(0, 0.9), (1, 0.8), (2, 0.9)]),
+        ]
+        .into_iter()
+        .collect();
+        let detector = Detector {
+            baseline: 2,
+            max_drop: 0.3,
+            max_rise: 1.0,
+        };
+
+        assert_eq!(
+            detector.detect(&series),
+            vec![
+                Anomaly {
+                    tag: "freedom".to_string(),
+                    at: 2,
+                    kind: AnomalyKind::Drop {
+                        baseline: 1.0,
+                        ratio: 0.5
+                    },
+                },
+                Anomaly {
+                    tag: "freedom".to_string(),
+                    at: 4,
+                    kind: AnomalyKind::Stopped,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn gap_rate_works() {
+        let history = vec![
+            tiv(0, 5, &["freedom"]),
+            tiv(6, 7, &["freedom"]),
+            tiv(8, 9, &["freedom"]),
+            tiv(10, 30, &["freedom"]),
+            tiv(0, 40, &["liberty"]),
+        ];
+        let tags: HashSet<String> = ["freedom", "liberty"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let series = gap_rate(&history, (10, 40), 10, 10, &tags);
+
+        assert_eq!(
+            series["freedom"],
+            vec![(10, 3.0), (20, 0.0), (30, 0.0), (40, 1.0)]
+        );
+        assert_eq!(
+            series["liberty"],
+            vec![(10, 0.0), (20, 0.0), (30, 0.0), (40, 0.0)]
+        );
+
+        let detector = Detector {
+            baseline: 2,
+            max_drop: 0.3,
+            max_rise: 0.5,
+        };
+        assert_eq!(
+            detector.detect_spikes(&series),
+            vec![Anomaly {
+                tag: "freedom".to_string(),
+                at: 40,
+                kind: AnomalyKind::Spike {
+                    baseline: 0.0,
+                    rate: 1.0
+                },
+            }]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{coverage, BoundOps, TaggedInterval};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+const WORD: usize = 64;
+
+/// The most cells a specified range may span before `difference` falls back
+/// to the sweep, which keeps the bitsets at 2 MiB per tag.
+const MAX_CELLS: i128 = 1 << 24;
+
+fn fill(bits: &mut [u64], from: usize, to: usize) {
+    let mut i = from;
+    while i < to {
+        let (word, bit) = (i / WORD, i % WORD);
+        if bit == 0 && to - i >= WORD {
+            bits[word] = !0;
+            i += WORD;
+        } else {
+            let end = (to - i).min(WORD - bit);
+            let mask = if end == WORD {
+                !0
+            } else {
+                ((1u64 << end) - 1) << bit
+            };
+            bits[word] |= mask;
+            i += end;
+        }
+    }
+}
+
+/// Computes the same result as `TaggedInterval::difference` with one bitset
+/// of cells per tag over the specified range, for integer bounds. Ranges of
+/// more than `MAX_CELLS` cells are left to the sweep.
+pub fn difference<Bound, M>(
+    specified: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+) -> Vec<TaggedInterval<Bound, M>>
+where
+    Bound: BoundOps + Into<i128> + TryFrom<i128>,
+    M: Clone + PartialEq,
+{
+    let origin: i128 = specified.lower.clone().into();
+    let span = specified.upper.clone().into() - origin;
+    if span > MAX_CELLS {
+        return coverage::sweep(specified, history, &coverage::Union);
+    }
+    let cells = span.max(0) as usize;
+    let cell = |b: &Bound| (b.clone().into() - origin).clamp(0, cells as i128) as usize;
+    let bound = |c: usize| match Bound::try_from(origin + c as i128) {
+        Ok(b) => b,
+        Err(_) => unreachable!("cells lie within the specified range"),
+    };
+
+    let mut tags: Vec<&String> = specified
+        .tags
+        .iter()
+        .chain(history.iter().flat_map(|iv| iv.tags.iter()))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    tags.sort();
+    let words = cells.div_ceil(WORD);
+    let mut bits = vec![vec![0u64; words]; tags.len()];
+    for iv in history {
+        let (from, to) = (cell(&iv.lower), cell(&iv.upper));
+        if from < to {
+            for (t, tag_bits) in tags.iter().zip(bits.iter_mut()) {
+                if iv.tags.contains(*t) {
+                    fill(tag_bits, from, to);
+                }
+            }
+        }
+    }
+
+    let state_at = |c: usize| -> Vec<bool> {
+        bits.iter()
+            .map(|b| b[c / WORD] >> (c % WORD) & 1 == 1)
+            .collect()
+    };
+    let mut result = vec![];
+    let mut emit = |lower: usize, upper: usize, covered: &[bool]| {
+        let missing: HashSet<String> = tags
+            .iter()
+            .zip(covered)
+            .filter(|(t, c)| !**c && specified.tags.contains(**t))
+            .map(|(t, _)| (*t).clone())
+            .collect();
+        if !missing.is_empty() {
+            result.push(TaggedInterval {
+                lower: bound(lower),
+                upper: bound(upper),
+                tags: missing,
+                meta: specified.meta.clone(),
+            });
+        }
+    };
+
+    let mut start = 0;
+    let mut current = if cells > 0 { state_at(0) } else { vec![] };
+    let mut c = 0;
+    while c < cells {
+        // Find the first cell from `c` in its word at which some tag differs
+        // from the current state.
+        let (word, bit) = (c / WORD, c % WORD);
+        let offset = bits
+            .iter()
+            .zip(current.iter())
+            .map(|(b, covered)| {
+                let expected = if *covered { !0 } else { 0 };
+                match (b[word] ^ expected) >> bit {
+                    0 => WORD - bit,
+                    diff => diff.trailing_zeros() as usize,
+                }
+            })
+            .min()
+            .unwrap_or(WORD - bit);
+        c = (c + offset).min(cells);
+        if offset < WORD - bit && c < cells {
+            emit(start, c, &current);
+            start = c;
+            current = state_at(c);
+        }
+    }
+    if start < cells {
+        emit(start, cells, &current);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{random_case, tiv, Lcg};
+
+    #[test]
+    fn difference_matches_sweep() {
+        let mut rng = Lcg(13);
+        for _ in 0..500 {
+            let (specified, history) = random_case(&mut rng, 100, 8);
+
+            assert_eq!(
+                difference(&specified, &history),
+                specified.clone().difference(history.clone()),
+                "{:?} {:?}",
+                specified,
+                history
+            );
+        }
+    }
+
+    #[test]
+    fn difference_falls_back_for_wide_ranges() {
+        let specified = tiv(i64::MIN, i64::MAX, &["freedom", "liberty"]);
+        let history = vec![
+            tiv(i64::MIN, 0, &["freedom"]),
+            tiv(-5, i64::MAX, &["liberty"]),
+        ];
+
+        assert_eq!(
+            difference(&specified, &history),
+            vec![
+                tiv(i64::MIN, -5, &["liberty"]),
+                tiv(0, i64::MAX, &["freedom"]),
+            ]
+        );
+    }
+}
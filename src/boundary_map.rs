@@ -0,0 +1,98 @@
+use crate::{BoundOps, TaggedInterval};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Computes the same result as `TaggedInterval::difference` from a map of
+/// per-tag coverage deltas keyed by bound instead of a sorted bound list.
+pub fn difference<Bound, M>(
+    specified: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+) -> Vec<TaggedInterval<Bound, M>>
+where
+    Bound: BoundOps,
+    M: Clone,
+{
+    let mut deltas: BTreeMap<Bound, HashMap<&String, i64>> = BTreeMap::new();
+    deltas.entry(specified.lower.clone()).or_default();
+    deltas.entry(specified.upper.clone()).or_default();
+    for iv in history {
+        for t in iv.tags.iter() {
+            *deltas
+                .entry(iv.lower.clone())
+                .or_default()
+                .entry(t)
+                .or_insert(0) += 1;
+            *deltas
+                .entry(iv.upper.clone())
+                .or_default()
+                .entry(t)
+                .or_insert(0) -= 1;
+        }
+    }
+
+    let mut result = vec![];
+    let mut counts: HashMap<&String, i64> = HashMap::new();
+    let mut segment: Option<(Bound, HashSet<&String>)> = None;
+
+    for (bound, delta) in deltas.range(..=specified.upper.clone()) {
+        for (&t, d) in delta.iter() {
+            *counts.entry(t).or_insert(0) += d;
+        }
+        counts.retain(|_, n| *n != 0);
+        if *bound < specified.lower {
+            continue;
+        }
+
+        let covered: HashSet<&String> = counts
+            .iter()
+            .filter(|(_, n)| **n > 0)
+            .map(|(t, _)| *t)
+            .collect();
+        if let Some((lower, current)) = segment.take() {
+            if current == covered && *bound < specified.upper {
+                segment = Some((lower, current));
+                continue;
+            }
+            let tags: HashSet<String> = specified
+                .tags
+                .iter()
+                .filter(|t| !current.contains(t))
+                .cloned()
+                .collect();
+            if !tags.is_empty() {
+                result.push(TaggedInterval {
+                    lower,
+                    upper: bound.clone(),
+                    tags,
+                    meta: specified.meta.clone(),
+                });
+            }
+        }
+        if *bound < specified.upper {
+            segment = Some((bound.clone(), covered));
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{random_case, Lcg};
+
+    #[test]
+    fn difference_matches_sweep() {
+        let mut rng = Lcg(7);
+        for _ in 0..500 {
+            let (specified, history) = random_case(&mut rng, 50, 8);
+
+            assert_eq!(
+                difference(&specified, &history),
+                specified.clone().difference(history.clone()),
+                "{:?} {:?}",
+                specified,
+                history
+            );
+        }
+    }
+}
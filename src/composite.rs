@@ -0,0 +1,124 @@
+use crate::{BoundOps, TaggedInterval};
+
+/// A bound over two dimensions, ordered lexicographically: by `first`, and
+/// by `second` within the same `first`, e.g. `(shard, time)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CompositeBound<A, B> {
+    pub first: A,
+    pub second: B,
+}
+
+impl<A, B> CompositeBound<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<A, B> From<(A, B)> for CompositeBound<A, B> {
+    fn from((first, second): (A, B)) -> Self {
+        Self::new(first, second)
+    }
+}
+
+/// Lifts `interval` into the composite space at `first`.
+pub fn within<A, B, M>(
+    first: A,
+    interval: TaggedInterval<B, M>,
+) -> TaggedInterval<CompositeBound<A, B>, M>
+where
+    A: BoundOps,
+    B: BoundOps,
+{
+    TaggedInterval {
+        lower: CompositeBound::new(first.clone(), interval.lower),
+        upper: CompositeBound::new(first, interval.upper),
+        tags: interval.tags,
+        meta: interval.meta,
+    }
+}
+
+/// Splits `interval` into one interval over the second dimension per value
+/// of `firsts` it spans. `extent` gives the full second-dimension range of a
+/// first value, used where `interval` runs through it from end to end.
+pub fn split<A, B, M, I, F>(
+    interval: &TaggedInterval<CompositeBound<A, B>, M>,
+    firsts: I,
+    extent: F,
+) -> Vec<(A, TaggedInterval<B, M>)>
+where
+    A: BoundOps,
+    B: BoundOps,
+    M: Clone,
+    I: IntoIterator<Item = A>,
+    F: Fn(&A) -> (B, B),
+{
+    let (lower, upper) = (&interval.lower, &interval.upper);
+    firsts
+        .into_iter()
+        .filter(|first| lower.first <= *first && *first <= upper.first)
+        .filter_map(|first| {
+            let (from, to) = extent(&first);
+            let from = if first == lower.first {
+                lower.second.clone().max(from)
+            } else {
+                from
+            };
+            let to = if first == upper.first {
+                upper.second.clone().min(to)
+            } else {
+                to
+            };
+            if from < to {
+                let part = TaggedInterval {
+                    lower: from,
+                    upper: to,
+                    tags: interval.tags.clone(),
+                    meta: interval.meta.clone(),
+                };
+                Some((first, part))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiv;
+
+    #[test]
+    fn composite_works() {
+        assert!(CompositeBound::new(0, 99) < CompositeBound::new(1, 0));
+        assert!(CompositeBound::new(1, 0) < CompositeBound::new(1, 1));
+
+        let specified = TaggedInterval::new(
+            CompositeBound::new(0u32, 50i64),
+            CompositeBound::new(2, 20),
+            ["freedom".to_string()].iter().cloned().collect(),
+        );
+        assert_eq!(
+            split(&specified, 0..4, |_| (0, 100)),
+            vec![
+                (0, tiv(50, 100, &["freedom"])),
+                (1, tiv(0, 100, &["freedom"])),
+                (2, tiv(0, 20, &["freedom"])),
+            ]
+        );
+
+        let history = vec![within(1, tiv(0, 100, &["freedom"]))];
+        let missing = specified.difference(history);
+        let parts: Vec<_> = missing
+            .iter()
+            .flat_map(|iv| split(iv, 0..4, |_| (0, 100)))
+            .collect();
+        assert_eq!(
+            parts,
+            vec![
+                (0, tiv(50, 100, &["freedom"])),
+                (2, tiv(0, 20, &["freedom"])),
+            ]
+        );
+    }
+}
@@ -0,0 +1,829 @@
+use crate::tag_set::TagSet;
+use crate::{BoundOps, ConfidenceAggregate, OutputTags, TaggedInterval, TieBreak};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::ControlFlow;
+
+pub trait Coverage<Bound, M = ()>
+where
+    Bound: BoundOps,
+{
+    type State;
+
+    fn empty(&self) -> Self::State;
+
+    /// Called when the sweep enters the history entry at `index`.
+    fn enter(&self, state: &mut Self::State, index: usize, entry: &TaggedInterval<Bound, M>);
+
+    /// Called when the sweep leaves the history entry at `index`.
+    fn exit(&self, state: &mut Self::State, index: usize, entry: &TaggedInterval<Bound, M>);
+
+    /// Returns the tags that count as covered in `state`.
+    fn covered(&self, state: &Self::State) -> HashSet<String>;
+
+    /// Returns the meta of output intervals emitted in `state`.
+    fn meta(&self, _state: &Self::State, specified: &TaggedInterval<Bound, M>) -> Option<M>
+    where
+        M: Clone,
+    {
+        specified.meta.clone()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BoundKind {
+    Specified,
+    History,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BoundDirection {
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct TaggedBound<Bound>
+where
+    Bound: BoundOps,
+{
+    kind: BoundKind,
+    direction: BoundDirection,
+    bound: Bound,
+    index: usize,
+}
+
+impl<Bound> TaggedBound<Bound>
+where
+    Bound: BoundOps,
+{
+    fn from_interval<M>(
+        interval: &TaggedInterval<Bound, M>,
+        kind: BoundKind,
+        index: usize,
+    ) -> (Self, Self) {
+        (
+            Self {
+                kind,
+                direction: BoundDirection::Lower,
+                bound: interval.lower.clone(),
+                index,
+            },
+            Self {
+                kind,
+                direction: BoundDirection::Upper,
+                bound: interval.upper.clone(),
+                index,
+            },
+        )
+    }
+
+    fn from_history<M>(intervals: &[TaggedInterval<Bound, M>]) -> Vec<Self> {
+        let mut bounds = vec![];
+        for (index, iv) in intervals.iter().enumerate() {
+            // Zero-length entries cover nothing.
+            if iv.lower == iv.upper {
+                continue;
+            }
+            let (lower, upper) = Self::from_interval(iv, BoundKind::History, index);
+            bounds.push(lower);
+            bounds.push(upper);
+        }
+        bounds
+    }
+
+    fn from_specified<M>(interval: &TaggedInterval<Bound, M>) -> Vec<Self> {
+        let mut bounds = vec![];
+        let (lower, upper) = Self::from_interval(interval, BoundKind::Specified, 0);
+        bounds.push(lower);
+        bounds.push(upper);
+        bounds
+    }
+
+    pub fn from_intervals<M>(
+        specified: &TaggedInterval<Bound, M>,
+        history: &[TaggedInterval<Bound, M>],
+    ) -> Vec<Self> {
+        [Self::from_specified(specified), Self::from_history(history)].concat()
+    }
+
+    pub fn sort(bounds: &mut Vec<Self>) -> &mut Vec<Self> {
+        bounds.sort_by(|x, y| x.bound.partial_cmp(&y.bound).unwrap());
+        bounds
+    }
+}
+
+/// Sweeps `history` over `specified` and returns the sub-ranges of
+/// `specified` with the specified tags that `coverage` does not count as
+/// covered there.
+pub fn sweep<Bound, M, C>(
+    specified: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+    coverage: &C,
+) -> Vec<TaggedInterval<Bound, M>>
+where
+    Bound: BoundOps,
+    M: Clone + PartialEq,
+    C: Coverage<Bound, M>,
+{
+    sweep_with_tie_break(specified, history, coverage, TieBreak::UpperFirst)
+}
+
+/// Like `sweep`, but with the given treatment of touching history entries.
+pub fn sweep_with_tie_break<Bound, M, C>(
+    specified: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+    coverage: &C,
+    tie_break: TieBreak,
+) -> Vec<TaggedInterval<Bound, M>>
+where
+    Bound: BoundOps,
+    M: Clone + PartialEq,
+    C: Coverage<Bound, M>,
+{
+    let mut bounds = TaggedBound::from_intervals(specified, history);
+    TaggedBound::sort(&mut bounds);
+
+    let mut result = vec![];
+    let mut in_specified_range = false;
+    let mut state = coverage.empty();
+    let mut active = HashSet::new();
+    let mut current_tags = coverage.covered(&state);
+    let mut current_meta = coverage.meta(&state, specified);
+    let mut current_bound = specified.lower.clone();
+    let num_bounds = bounds.len();
+    let mut i = 0;
+
+    while i < num_bounds {
+        let mut specified_lower_found = false;
+        let mut specified_range_will_be_over = false;
+        let mut lower_entries = vec![];
+        let mut upper_entries = vec![];
+        let mut j = i;
+
+        while j < num_bounds && bounds[j].bound.eq(&bounds[i].bound) {
+            match bounds[j].kind {
+                BoundKind::History => match bounds[j].direction {
+                    BoundDirection::Lower => {
+                        lower_entries.push(bounds[j].index);
+                    }
+                    BoundDirection::Upper => {
+                        upper_entries.push(bounds[j].index);
+                    }
+                },
+                BoundKind::Specified => match bounds[j].direction {
+                    BoundDirection::Lower => {
+                        specified_lower_found = true;
+                    }
+                    BoundDirection::Upper => {
+                        specified_range_will_be_over = true;
+                    }
+                },
+            }
+            j += 1;
+        }
+
+        let seam = tie_break == TieBreak::LowerFirst
+            && specified.tags.iter().any(|t| {
+                upper_entries.iter().any(|i| history[*i].tags.contains(t))
+                    && lower_entries.iter().any(|i| history[*i].tags.contains(t))
+            });
+        let exit_all = |state: &mut C::State, active: &mut HashSet<usize>| {
+            for index in upper_entries.iter() {
+                if active.remove(index) {
+                    coverage.exit(state, *index, &history[*index]);
+                }
+            }
+        };
+        if tie_break == TieBreak::UpperFirst {
+            exit_all(&mut state, &mut active);
+        }
+        for index in lower_entries {
+            if active.insert(index) {
+                coverage.enter(&mut state, index, &history[index]);
+            }
+        }
+        if tie_break == TieBreak::LowerFirst {
+            exit_all(&mut state, &mut active);
+        }
+
+        let next_tags = coverage.covered(&state);
+        let next_meta = coverage.meta(&state, specified);
+        let continuous = in_specified_range
+            && next_tags.eq(&current_tags)
+            && next_meta.eq(&current_meta)
+            && !seam;
+
+        if in_specified_range && (!continuous || specified_range_will_be_over) {
+            let tags: HashSet<String> = specified.tags.difference(&current_tags).cloned().collect();
+            if !tags.is_empty() {
+                result.push(TaggedInterval {
+                    lower: current_bound.clone(),
+                    upper: bounds[i].bound.clone(),
+                    tags,
+                    meta: current_meta.clone(),
+                });
+            }
+        }
+
+        if specified_range_will_be_over {
+            break;
+        }
+        if specified_lower_found {
+            in_specified_range = true;
+        }
+        if !continuous {
+            current_bound = bounds[i].bound.clone();
+        }
+
+        i = j;
+        current_tags = next_tags;
+        current_meta = next_meta;
+    }
+
+    result
+}
+
+/// Computes `sweep` with `Union` keeping the covered tags as interned IDs
+/// in `S`, or returns `None` if there are more distinct tags than `S` holds.
+pub fn union_with<S, Bound, M>(
+    specified: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+) -> Option<Vec<TaggedInterval<Bound, M>>>
+where
+    S: TagSet,
+    Bound: BoundOps,
+    M: Clone,
+{
+    let mut result = vec![];
+    let _ = union_each::<S, _, _, _>(specified, history, |iv| {
+        result.push(iv);
+        ControlFlow::Continue(())
+    })?;
+    Some(result)
+}
+
+/// Like `union_with`, but passes each output interval to `f` as soon as it
+/// is found, stopping when `f` breaks.
+pub fn union_each<S, Bound, M, F>(
+    specified: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+    f: F,
+) -> Option<ControlFlow<()>>
+where
+    S: TagSet,
+    Bound: BoundOps,
+    M: Clone,
+    F: FnMut(TaggedInterval<Bound, M>) -> ControlFlow<()>,
+{
+    let ids = TagIds::new(specified, history);
+    if S::CAPACITY.is_some_and(|capacity| ids.names.len() > capacity) {
+        return None;
+    }
+    Some(union_ids::<S, _, _, _>(specified, history, &ids, f))
+}
+
+/// Like `union_each`, but keeps the covered tags in the smallest of `u64`,
+/// `u128` and `HashSet<usize>` that holds them, interning the tags once.
+pub fn union_fitting<Bound, M, F>(
+    specified: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+    f: F,
+) -> ControlFlow<()>
+where
+    Bound: BoundOps,
+    M: Clone,
+    F: FnMut(TaggedInterval<Bound, M>) -> ControlFlow<()>,
+{
+    let ids = TagIds::new(specified, history);
+    match ids.names.len() {
+        n if n <= 64 => union_ids::<u64, _, _, _>(specified, history, &ids, f),
+        n if n <= 128 => union_ids::<u128, _, _, _>(specified, history, &ids, f),
+        _ => union_ids::<HashSet<usize>, _, _, _>(specified, history, &ids, f),
+    }
+}
+
+/// The tags of a sweep numbered in order of appearance.
+struct TagIds<'a> {
+    names: Vec<&'a String>,
+    specified: Vec<usize>,
+    /// The IDs of the tags of each history entry.
+    entries: Vec<Vec<usize>>,
+}
+
+impl<'a> TagIds<'a> {
+    fn new<Bound, M>(
+        specified: &'a TaggedInterval<Bound, M>,
+        history: &'a [TaggedInterval<Bound, M>],
+    ) -> Self
+    where
+        Bound: BoundOps,
+    {
+        let mut names: Vec<&String> = vec![];
+        let mut ids: HashMap<&String, usize> = HashMap::new();
+        let mut intern = |tags: &'a HashSet<String>| -> Vec<usize> {
+            tags.iter()
+                .map(|t| {
+                    *ids.entry(t).or_insert_with(|| {
+                        names.push(t);
+                        names.len() - 1
+                    })
+                })
+                .collect()
+        };
+        let specified = intern(&specified.tags);
+        let entries = history.iter().map(|iv| intern(&iv.tags)).collect();
+        Self {
+            names,
+            specified,
+            entries,
+        }
+    }
+}
+
+fn union_ids<S, Bound, M, F>(
+    specified: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+    ids: &TagIds,
+    mut f: F,
+) -> ControlFlow<()>
+where
+    S: TagSet,
+    Bound: BoundOps,
+    M: Clone,
+    F: FnMut(TaggedInterval<Bound, M>) -> ControlFlow<()>,
+{
+    let TagIds {
+        names,
+        specified: wanted_ids,
+        entries,
+    } = ids;
+    let mut wanted = S::default();
+    wanted_ids.iter().for_each(|id| wanted.insert(*id));
+
+    let mut bounds = TaggedBound::from_intervals(specified, history);
+    TaggedBound::sort(&mut bounds);
+
+    let mut in_specified_range = false;
+    let mut counts = vec![0usize; names.len()];
+    let mut active = vec![false; history.len()];
+    let mut current = S::default();
+    let mut current_bound = specified.lower.clone();
+    let mut i = 0;
+
+    while i < bounds.len() {
+        let mut specified_lower_found = false;
+        let mut specified_range_will_be_over = false;
+        let mut next = current.clone();
+        let mut j = i;
+
+        // Leave before entering, as `sweep` does.
+        while j < bounds.len() && bounds[j].bound == bounds[i].bound {
+            let b = &bounds[j];
+            match (b.kind, b.direction) {
+                (BoundKind::Specified, BoundDirection::Lower) => specified_lower_found = true,
+                (BoundKind::Specified, BoundDirection::Upper) => {
+                    specified_range_will_be_over = true
+                }
+                (BoundKind::History, BoundDirection::Upper) if active[b.index] => {
+                    active[b.index] = false;
+                    for id in entries[b.index].iter() {
+                        counts[*id] -= 1;
+                        if counts[*id] == 0 {
+                            next.remove(*id);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            j += 1;
+        }
+        for b in bounds[i..j].iter() {
+            if b.kind == BoundKind::History
+                && b.direction == BoundDirection::Lower
+                && !active[b.index]
+            {
+                active[b.index] = true;
+                for id in entries[b.index].iter() {
+                    counts[*id] += 1;
+                    next.insert(*id);
+                }
+            }
+        }
+
+        let continuous = in_specified_range && next == current;
+        if in_specified_range && (!continuous || specified_range_will_be_over) {
+            let missing = wanted.difference(&current);
+            if !missing.is_empty() {
+                let iv = TaggedInterval {
+                    lower: current_bound.clone(),
+                    upper: bounds[i].bound.clone(),
+                    tags: missing.into_iter().map(|id| names[id].clone()).collect(),
+                    meta: specified.meta.clone(),
+                };
+                if f(iv).is_break() {
+                    return ControlFlow::Break(());
+                }
+            }
+        }
+
+        if specified_range_will_be_over {
+            break;
+        }
+        if specified_lower_found {
+            in_specified_range = true;
+        }
+        if !continuous {
+            current_bound = bounds[i].bound.clone();
+        }
+
+        i = j;
+        current = next;
+    }
+
+    ControlFlow::Continue(())
+}
+
+fn add_tags(counts: &mut HashMap<String, usize>, tags: &HashSet<String>) {
+    tags.iter()
+        .for_each(|t| *counts.entry(t.clone()).or_insert(0) += 1);
+}
+
+fn remove_tags(counts: &mut HashMap<String, usize>, tags: &HashSet<String>) {
+    for t in tags {
+        if let Some(n) = counts.get_mut(t) {
+            *n -= 1;
+            if *n == 0 {
+                counts.remove(t);
+            }
+        }
+    }
+}
+
+/// A tag is covered wherever any history entry carries it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Union;
+
+impl<Bound, M> Coverage<Bound, M> for Union
+where
+    Bound: BoundOps,
+{
+    type State = HashMap<String, usize>;
+
+    fn empty(&self) -> Self::State {
+        HashMap::new()
+    }
+
+    fn enter(&self, state: &mut Self::State, _index: usize, entry: &TaggedInterval<Bound, M>) {
+        add_tags(state, &entry.tags);
+    }
+
+    fn exit(&self, state: &mut Self::State, _index: usize, entry: &TaggedInterval<Bound, M>) {
+        remove_tags(state, &entry.tags);
+    }
+
+    fn covered(&self, state: &Self::State) -> HashSet<String> {
+        state.keys().cloned().collect()
+    }
+}
+
+/// A tag is covered wherever at least the given number of history entries
+/// carry it. Every entry counts, identical ones included.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtLeast(pub usize);
+
+impl<Bound, M> Coverage<Bound, M> for AtLeast
+where
+    Bound: BoundOps,
+{
+    type State = HashMap<String, usize>;
+
+    fn empty(&self) -> Self::State {
+        HashMap::new()
+    }
+
+    fn enter(&self, state: &mut Self::State, _index: usize, entry: &TaggedInterval<Bound, M>) {
+        add_tags(state, &entry.tags);
+    }
+
+    fn exit(&self, state: &mut Self::State, _index: usize, entry: &TaggedInterval<Bound, M>) {
+        remove_tags(state, &entry.tags);
+    }
+
+    fn covered(&self, state: &Self::State) -> HashSet<String> {
+        state
+            .iter()
+            .filter(|(_, n)| **n >= self.0)
+            .map(|(t, _)| t.clone())
+            .collect()
+    }
+}
+
+/// A tag is covered wherever the aggregated confidence of the history entries
+/// carrying it exceeds `threshold`. `confidences` is indexed like the history.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Weighted<'a> {
+    pub confidences: &'a [f64],
+    pub threshold: f64,
+    pub aggregate: ConfidenceAggregate,
+}
+
+impl<'a, Bound, M> Coverage<Bound, M> for Weighted<'a>
+where
+    Bound: BoundOps,
+{
+    type State = HashMap<String, Vec<usize>>;
+
+    fn empty(&self) -> Self::State {
+        HashMap::new()
+    }
+
+    fn enter(&self, state: &mut Self::State, index: usize, entry: &TaggedInterval<Bound, M>) {
+        entry
+            .tags
+            .iter()
+            .for_each(|t| state.entry(t.clone()).or_default().push(index));
+    }
+
+    fn exit(&self, state: &mut Self::State, index: usize, entry: &TaggedInterval<Bound, M>) {
+        for t in entry.tags.iter() {
+            if let Some(indices) = state.get_mut(t) {
+                indices.retain(|i| *i != index);
+                if indices.is_empty() {
+                    state.remove(t);
+                }
+            }
+        }
+    }
+
+    fn covered(&self, state: &Self::State) -> HashSet<String> {
+        state
+            .iter()
+            .filter(|(_, indices)| {
+                let confidences = indices.iter().map(|&i| self.confidences[i]);
+                let score = match self.aggregate {
+                    ConfidenceAggregate::Max => confidences.fold(f64::NEG_INFINITY, f64::max),
+                    ConfidenceAggregate::Sum => confidences.sum(),
+                };
+                score > self.threshold
+            })
+            .map(|(t, _)| t.clone())
+            .collect()
+    }
+}
+
+/// For each tag, the latest history entry spanning a point decides whether it
+/// is covered there. `revoked` is indexed like the history.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LastWriterWins<'a> {
+    pub revoked: &'a [bool],
+}
+
+impl<'a, Bound, M> Coverage<Bound, M> for LastWriterWins<'a>
+where
+    Bound: BoundOps,
+{
+    type State = HashMap<String, BTreeSet<usize>>;
+
+    fn empty(&self) -> Self::State {
+        HashMap::new()
+    }
+
+    fn enter(&self, state: &mut Self::State, index: usize, entry: &TaggedInterval<Bound, M>) {
+        entry.tags.iter().for_each(|t| {
+            state.entry(t.clone()).or_default().insert(index);
+        });
+    }
+
+    fn exit(&self, state: &mut Self::State, index: usize, entry: &TaggedInterval<Bound, M>) {
+        for t in entry.tags.iter() {
+            if let Some(indices) = state.get_mut(t) {
+                indices.remove(&index);
+                if indices.is_empty() {
+                    state.remove(t);
+                }
+            }
+        }
+    }
+
+    fn covered(&self, state: &Self::State) -> HashSet<String> {
+        state
+            .iter()
+            .filter(|(_, indices)| {
+                indices
+                    .iter()
+                    .next_back()
+                    .is_some_and(|&i| !self.revoked[i])
+            })
+            .map(|(t, _)| t.clone())
+            .collect()
+    }
+}
+
+pub(crate) struct ByOutputTags<'a, Bound, M, S>
+where
+    Bound: BoundOps,
+{
+    pub strategy: &'a S,
+    pub specified: &'a TaggedInterval<Bound, M>,
+    pub history: &'a [TaggedInterval<Bound, M>],
+}
+
+impl<'a, Bound, M, S> Coverage<Bound, M> for ByOutputTags<'a, Bound, M, S>
+where
+    Bound: BoundOps,
+    S: OutputTags<Bound, M>,
+{
+    type State = BTreeSet<usize>;
+
+    fn empty(&self) -> Self::State {
+        BTreeSet::new()
+    }
+
+    fn enter(&self, state: &mut Self::State, index: usize, _entry: &TaggedInterval<Bound, M>) {
+        state.insert(index);
+    }
+
+    fn exit(&self, state: &mut Self::State, index: usize, _entry: &TaggedInterval<Bound, M>) {
+        state.remove(&index);
+    }
+
+    fn covered(&self, state: &Self::State) -> HashSet<String> {
+        let covering: Vec<&TaggedInterval<Bound, M>> =
+            state.iter().map(|&i| &self.history[i]).collect();
+        let output = self.strategy.output_tags(self.specified, &covering);
+        self.specified.tags.difference(&output).cloned().collect()
+    }
+}
+
+pub(crate) struct MergedMeta<'a, Bound, M, F>
+where
+    Bound: BoundOps,
+{
+    pub merge: F,
+    pub history: &'a [TaggedInterval<Bound, M>],
+}
+
+impl<'a, Bound, M, F> Coverage<Bound, M> for MergedMeta<'a, Bound, M, F>
+where
+    Bound: BoundOps,
+    F: Fn(Option<&M>, &[&M]) -> Option<M>,
+{
+    type State = BTreeSet<usize>;
+
+    fn empty(&self) -> Self::State {
+        BTreeSet::new()
+    }
+
+    fn enter(&self, state: &mut Self::State, index: usize, _entry: &TaggedInterval<Bound, M>) {
+        state.insert(index);
+    }
+
+    fn exit(&self, state: &mut Self::State, index: usize, _entry: &TaggedInterval<Bound, M>) {
+        state.remove(&index);
+    }
+
+    fn covered(&self, state: &Self::State) -> HashSet<String> {
+        state
+            .iter()
+            .flat_map(|&i| self.history[i].tags.iter().cloned())
+            .collect()
+    }
+
+    fn meta(&self, state: &Self::State, specified: &TaggedInterval<Bound, M>) -> Option<M>
+    where
+        M: Clone,
+    {
+        let metas: Vec<&M> = state
+            .iter()
+            .filter_map(|&i| self.history[i].meta.as_ref())
+            .collect();
+        (self.merge)(specified.meta.as_ref(), &metas)
+    }
+}
+
+pub(crate) struct Complement<'a, C> {
+    pub inner: &'a C,
+    pub tags: &'a HashSet<String>,
+}
+
+impl<'a, Bound, M, C> Coverage<Bound, M> for Complement<'a, C>
+where
+    Bound: BoundOps,
+    C: Coverage<Bound, M>,
+{
+    type State = C::State;
+
+    fn empty(&self) -> Self::State {
+        self.inner.empty()
+    }
+
+    fn enter(&self, state: &mut Self::State, index: usize, entry: &TaggedInterval<Bound, M>) {
+        self.inner.enter(state, index, entry);
+    }
+
+    fn exit(&self, state: &mut Self::State, index: usize, entry: &TaggedInterval<Bound, M>) {
+        self.inner.exit(state, index, entry);
+    }
+
+    fn covered(&self, state: &Self::State) -> HashSet<String> {
+        self.tags
+            .difference(&self.inner.covered(state))
+            .cloned()
+            .collect()
+    }
+
+    fn meta(&self, state: &Self::State, specified: &TaggedInterval<Bound, M>) -> Option<M>
+    where
+        M: Clone,
+    {
+        self.inner.meta(state, specified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag_set::Interned;
+    use crate::{random_case, tiv, Lcg};
+
+    #[test]
+    fn sweep_works() {
+        struct Exclusive;
+
+        impl Coverage<i64> for Exclusive {
+            type State = HashMap<String, usize>;
+
+            fn empty(&self) -> Self::State {
+                HashMap::new()
+            }
+
+            fn enter(&self, state: &mut Self::State, _index: usize, entry: &TaggedInterval<i64>) {
+                add_tags(state, &entry.tags);
+            }
+
+            fn exit(&self, state: &mut Self::State, _index: usize, entry: &TaggedInterval<i64>) {
+                remove_tags(state, &entry.tags);
+            }
+
+            fn covered(&self, state: &Self::State) -> HashSet<String> {
+                state
+                    .iter()
+                    .filter(|(_, n)| **n == 1)
+                    .map(|(t, _)| t.clone())
+                    .collect()
+            }
+        }
+
+        let specified = tiv(0, 10, &["freedom"]);
+        let history = vec![tiv(0, 6, &["freedom"]), tiv(4, 10, &["freedom"])];
+
+        assert_eq!(sweep(&specified, &history, &Union), vec![]);
+        assert_eq!(
+            sweep(&specified, &history, &AtLeast(2)),
+            vec![tiv(0, 4, &["freedom"]), tiv(6, 10, &["freedom"])]
+        );
+        assert_eq!(
+            sweep(&specified, &history, &Exclusive),
+            vec![tiv(4, 6, &["freedom"])]
+        );
+    }
+
+    #[test]
+    fn union_with_matches_sweep() {
+        let mut rng = Lcg(17);
+        for _ in 0..500 {
+            let (specified, history) = random_case(&mut rng, 50, 8);
+
+            let expected = Some(sweep(&specified, &history, &Union));
+            assert_eq!(union_with::<u64, _, _>(&specified, &history), expected);
+            assert_eq!(
+                union_with::<BTreeSet<usize>, _, _>(&specified, &history),
+                expected
+            );
+            assert_eq!(union_with::<Interned, _, _>(&specified, &history), expected);
+        }
+
+        let many: Vec<String> = (0..65).map(|i| format!("tag{}", i)).collect();
+        let specified = TaggedInterval::new(0, 10, many.into_iter().collect());
+        assert_eq!(union_with::<u64, _, _>(&specified, &[]), None);
+        assert!(union_with::<u128, _, _>(&specified, &[]).is_some());
+
+        for n in [64, 65, 129] {
+            let tags: Vec<String> = (0..n).map(|i| format!("tag{}", i)).collect();
+            let specified = TaggedInterval::new(0, 10, tags.iter().cloned().collect());
+            let history: Vec<_> = tags
+                .iter()
+                .enumerate()
+                .map(|(i, t)| {
+                    TaggedInterval::new(i as i64 % 10, 10, [t.clone()].iter().cloned().collect())
+                })
+                .collect();
+            let mut fitting = vec![];
+            let _ = union_fitting(&specified, &history, |iv| {
+                fitting.push(iv);
+                ControlFlow::Continue(())
+            });
+            assert_eq!(fitting, sweep(&specified, &history, &Union), "{}", n);
+        }
+    }
+}
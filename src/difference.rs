@@ -0,0 +1,190 @@
+use crate::{flatten_per_tag, BoundOps, Length, TaggedInterval};
+use std::collections::HashMap;
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut, Sub};
+
+/// The result of `TaggedInterval::difference` and its variants, with helpers
+/// for post-processing it. It dereferences to a slice and compares equal to
+/// a `Vec` of the same intervals; `into_vec` returns the intervals. With the
+/// `serde` feature it serializes as that `Vec`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct Difference<Bound, M = ()>(Vec<TaggedInterval<Bound, M>>)
+where
+    Bound: BoundOps;
+
+impl<Bound, M> Difference<Bound, M>
+where
+    Bound: BoundOps,
+{
+    pub fn into_vec(self) -> Vec<TaggedInterval<Bound, M>> {
+        self.0
+    }
+
+    /// Returns the missing length summed over tags, so a range missing two
+    /// tags counts twice.
+    pub fn total_length<D>(&self) -> D
+    where
+        Bound: Sub<Output = D>,
+        D: Length,
+    {
+        self.0.iter().fold(D::default(), |sum, iv| {
+            (0..iv.tags.len()).fold(sum, |sum, _| sum + (iv.upper.clone() - iv.lower.clone()))
+        })
+    }
+
+    /// Returns the missing ranges of each tag, as `flatten_per_tag` does.
+    pub fn by_tag(&self) -> HashMap<String, Vec<(Bound, Bound)>> {
+        flatten_per_tag(&self.0)
+    }
+}
+
+impl<Bound, M> Default for Difference<Bound, M>
+where
+    Bound: BoundOps,
+{
+    fn default() -> Self {
+        Self(vec![])
+    }
+}
+
+impl<Bound, M> From<Vec<TaggedInterval<Bound, M>>> for Difference<Bound, M>
+where
+    Bound: BoundOps,
+{
+    fn from(intervals: Vec<TaggedInterval<Bound, M>>) -> Self {
+        Self(intervals)
+    }
+}
+
+impl<Bound, M> From<Difference<Bound, M>> for Vec<TaggedInterval<Bound, M>>
+where
+    Bound: BoundOps,
+{
+    fn from(difference: Difference<Bound, M>) -> Self {
+        difference.0
+    }
+}
+
+impl<Bound, M> FromIterator<TaggedInterval<Bound, M>> for Difference<Bound, M>
+where
+    Bound: BoundOps,
+{
+    fn from_iter<I>(intervals: I) -> Self
+    where
+        I: IntoIterator<Item = TaggedInterval<Bound, M>>,
+    {
+        Self(intervals.into_iter().collect())
+    }
+}
+
+impl<Bound, M> PartialEq<Vec<TaggedInterval<Bound, M>>> for Difference<Bound, M>
+where
+    Bound: BoundOps,
+    M: PartialEq,
+{
+    fn eq(&self, other: &Vec<TaggedInterval<Bound, M>>) -> bool {
+        self.0 == *other
+    }
+}
+
+impl<Bound, M> PartialEq<Difference<Bound, M>> for Vec<TaggedInterval<Bound, M>>
+where
+    Bound: BoundOps,
+    M: PartialEq,
+{
+    fn eq(&self, other: &Difference<Bound, M>) -> bool {
+        *self == other.0
+    }
+}
+
+impl<Bound, M> Deref for Difference<Bound, M>
+where
+    Bound: BoundOps,
+{
+    type Target = [TaggedInterval<Bound, M>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<Bound, M> DerefMut for Difference<Bound, M>
+where
+    Bound: BoundOps,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<Bound, M> IntoIterator for Difference<Bound, M>
+where
+    Bound: BoundOps,
+{
+    type Item = TaggedInterval<Bound, M>;
+    type IntoIter = std::vec::IntoIter<TaggedInterval<Bound, M>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, Bound, M> IntoIterator for &'a Difference<Bound, M>
+where
+    Bound: BoundOps,
+{
+    type Item = &'a TaggedInterval<Bound, M>;
+    type IntoIter = std::slice::Iter<'a, TaggedInterval<Bound, M>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiv;
+
+    #[test]
+    fn difference_works() {
+        let specified = tiv(0, 100, &["freedom", "liberty"]);
+        let history = vec![tiv(10, 90, &["freedom"]), tiv(0, 50, &["liberty"])];
+        let missing = specified.difference(history);
+
+        assert_eq!(missing.len(), 3);
+        assert!(!missing.is_empty());
+        assert_eq!(missing.total_length(), 70);
+        assert_eq!(missing.by_tag()["freedom"], vec![(0, 10), (90, 100)]);
+        assert_eq!(missing.by_tag()["liberty"], vec![(50, 100)]);
+        assert_eq!((&missing).into_iter().count(), 3);
+        assert_eq!(missing.iter().cloned().collect::<Difference<_>>(), missing);
+        assert_eq!(Vec::from(missing.clone()), missing);
+        assert_eq!(missing.into_vec()[0], tiv(0, 10, &["freedom"]));
+
+        let none: Difference<i64> = Vec::new().into();
+        assert!(none.is_empty());
+        assert_eq!(none.total_length(), 0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn difference_serializes_as_vec() {
+        let missing: Difference<i64> = vec![tiv(0, 10, &["freedom"])].into();
+        let json = serde_json::to_string(&missing).unwrap();
+
+        assert_eq!(
+            json,
+            r#"[{"lower":0,"upper":10,"tags":["freedom"],"meta":null}]"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Difference<i64>>(&json).unwrap(),
+            missing
+        );
+    }
+}
@@ -0,0 +1,1236 @@
+use crate::timeline::Timeline;
+use crate::{flatten_per_tag, BoundOps, Length, TaggedInterval};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::Sub;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+
+/// A window relative to the evaluation time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Window {
+    /// The given duration up to now.
+    Last(Duration),
+    /// Yesterday, from midnight to midnight.
+    PreviousDay,
+    /// The previous calendar month, from its first day to the first day of
+    /// the current month.
+    PreviousMonth,
+}
+
+/// A window relative to "now" with the tags it asks for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Goal {
+    pub window: Window,
+    pub tags: HashSet<String>,
+}
+
+impl Goal {
+    pub fn new(window: Window, tags: HashSet<String>) -> Self {
+        Self { window, tags }
+    }
+
+    /// Resolves the goal at `now`. Calendar windows follow the time zone of
+    /// `now`; `None` is returned if a boundary midnight does not exist there.
+    pub fn resolve<Tz>(&self, now: DateTime<Tz>) -> Option<TaggedInterval<DateTime<Tz>>>
+    where
+        Tz: TimeZone,
+        DateTime<Tz>: BoundOps,
+    {
+        let tz = now.timezone();
+        let midnight = |date: NaiveDate| {
+            tz.from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+                .earliest()
+        };
+        let today = now.date_naive();
+
+        let (lower, upper) = match self.window {
+            Window::Last(length) => (now.clone() - length, now),
+            Window::PreviousDay => (midnight(today.pred_opt()?)?, midnight(today)?),
+            Window::PreviousMonth => {
+                let first = today.with_day(1)?;
+                let previous = first.pred_opt()?.with_day(1)?;
+                (midnight(previous)?, midnight(first)?)
+            }
+        };
+        Some(TaggedInterval::new(lower, upper, self.tags.clone()))
+    }
+}
+
+/// How the coverage of a watched range changed with a write.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoverageChange<Bound>
+where
+    Bound: BoundOps,
+{
+    /// The ranges and tags covered now but not before.
+    pub covered: Vec<TaggedInterval<Bound>>,
+    /// The ranges and tags covered before but not now.
+    pub uncovered: Vec<TaggedInterval<Bound>>,
+}
+
+#[derive(Debug)]
+struct Watch<Bound>
+where
+    Bound: BoundOps,
+{
+    range: TaggedInterval<Bound>,
+    sender: Sender<CoverageChange<Bound>>,
+}
+
+#[derive(Debug)]
+struct Completion {
+    name: String,
+    sender: Sender<String>,
+}
+
+/// Named goals checked against one shared history.
+///
+/// The state is shared between clones and copied on the first write, so
+/// `snapshot` is cheap and readers of a snapshot never see later writes.
+/// Watches, including completion watches, are not cloned and are ignored
+/// by comparisons. With the `serde` feature the rest of the state can be
+/// saved and restored; compacting the history with `record_all` first
+/// keeps it small.
+#[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "Bound: serde::Serialize",
+        deserialize = "Bound: serde::Deserialize<'de>"
+    ))
+)]
+pub struct GoalSet<Bound>
+where
+    Bound: BoundOps,
+{
+    goals: Arc<BTreeMap<String, TaggedInterval<Bound>>>,
+    history: Arc<Vec<TaggedInterval<Bound>>>,
+    /// The index into `history` of each entry recorded with a key.
+    keys: Arc<HashMap<String, usize>>,
+    /// The entries begun but not yet committed, by key.
+    pending: Arc<HashMap<String, TaggedInterval<Bound>>>,
+    /// The missing spans of each goal, per tag, with the time each was
+    /// first found missing, as of the last `missing_with_age`.
+    ages: Arc<HashMap<String, Ages<Bound>>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    watches: Vec<Watch<Bound>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    completions: Vec<Completion>,
+}
+
+type Ages<Bound> = HashMap<String, Vec<(Bound, Bound, Bound)>>;
+
+impl<Bound> Default for GoalSet<Bound>
+where
+    Bound: BoundOps,
+{
+    fn default() -> Self {
+        Self {
+            goals: Arc::default(),
+            history: Arc::default(),
+            keys: Arc::default(),
+            pending: Arc::default(),
+            ages: Arc::default(),
+            watches: vec![],
+            completions: vec![],
+        }
+    }
+}
+
+impl<Bound> Clone for GoalSet<Bound>
+where
+    Bound: BoundOps,
+{
+    fn clone(&self) -> Self {
+        Self {
+            goals: Arc::clone(&self.goals),
+            history: Arc::clone(&self.history),
+            keys: Arc::clone(&self.keys),
+            pending: Arc::clone(&self.pending),
+            ages: Arc::clone(&self.ages),
+            watches: vec![],
+            completions: vec![],
+        }
+    }
+}
+
+impl<Bound> PartialEq for GoalSet<Bound>
+where
+    Bound: BoundOps,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.goals == other.goals
+            && self.history == other.history
+            && self.keys == other.keys
+            && self.pending == other.pending
+            && self.ages == other.ages
+    }
+}
+
+impl<Bound> GoalSet<Bound>
+where
+    Bound: BoundOps,
+{
+    /// Adds or replaces the goal named `name`.
+    pub fn insert(&mut self, name: &str, goal: TaggedInterval<Bound>) {
+        Arc::make_mut(&mut self.goals).insert(name.to_string(), goal);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<TaggedInterval<Bound>> {
+        if !self.goals.contains_key(name) {
+            return None;
+        }
+        if self.ages.contains_key(name) {
+            Arc::make_mut(&mut self.ages).remove(name);
+        }
+        Arc::make_mut(&mut self.goals).remove(name)
+    }
+
+    /// Records `entry` and returns the parts of it that were not covered
+    /// before, e.g. to spot ranges that are fetched twice.
+    pub fn record(&mut self, entry: TaggedInterval<Bound>) -> Vec<TaggedInterval<Bound>> {
+        let history = crate::clip_history(&entry, &self.history);
+        let gained = entry.clone().difference(history).into_vec();
+        self.notify(extent(&[&entry]), |set| {
+            Arc::make_mut(&mut set.history).push(entry)
+        });
+        gained
+    }
+
+    /// Records a batch of entries, then merges everything recorded without
+    /// a key into disjoint intervals, once for the whole batch.
+    pub fn record_all<I>(&mut self, entries: I)
+    where
+        I: IntoIterator<Item = TaggedInterval<Bound>>,
+    {
+        let entries: Vec<_> = entries.into_iter().collect();
+        self.notify(extent(&entries.iter().collect::<Vec<_>>()), |set| {
+            Arc::make_mut(&mut set.history).extend(entries);
+            set.compact();
+        });
+    }
+
+    /// Merges the entries recorded without a key with `union_all`. Keyed
+    /// entries are kept as they are so that they can be retracted.
+    fn compact(&mut self) {
+        let keys = Arc::make_mut(&mut self.keys);
+        let history = Arc::make_mut(&mut self.history);
+        let mut moved = HashMap::new();
+        let mut keyed = vec![];
+        let mut unkeyed = vec![];
+        let indices: HashSet<usize> = keys.values().cloned().collect();
+        for (i, entry) in history.drain(..).enumerate() {
+            if indices.contains(&i) {
+                moved.insert(i, keyed.len());
+                keyed.push(entry);
+            } else {
+                unkeyed.push(entry);
+            }
+        }
+        keys.values_mut().for_each(|i| *i = moved[i]);
+        keyed.extend(TaggedInterval::union_all(unkeyed));
+        *history = keyed;
+    }
+
+    /// Records `entry` under `key` unless an entry was already recorded
+    /// under it, so that redelivered entries are recorded once. Returns
+    /// whether `entry` was recorded.
+    pub fn record_keyed(&mut self, key: &str, entry: TaggedInterval<Bound>) -> bool {
+        if self.keys.contains_key(key) {
+            return false;
+        }
+        self.notify(extent(&[&entry]), |set| {
+            Arc::make_mut(&mut set.keys).insert(key.to_string(), set.history.len());
+            Arc::make_mut(&mut set.history).push(entry);
+        });
+        true
+    }
+
+    /// Marks `entry` as being fetched under `key`. A pending entry is still
+    /// missing, but `combined_missing` leaves it out so that it is not
+    /// fetched again. Returns whether `key` was free.
+    pub fn begin(&mut self, key: &str, entry: TaggedInterval<Bound>) -> bool {
+        if self.keys.contains_key(key) || self.pending.contains_key(key) {
+            return false;
+        }
+        Arc::make_mut(&mut self.pending).insert(key.to_string(), entry);
+        true
+    }
+
+    /// Records the entry begun under `key` as if by `record_keyed`. Returns
+    /// whether there was one.
+    pub fn commit(&mut self, key: &str) -> bool {
+        match Arc::make_mut(&mut self.pending).remove(key) {
+            Some(entry) => self.record_keyed(key, entry),
+            None => false,
+        }
+    }
+
+    /// Drops the entry begun under `key`, if any, without recording it. The
+    /// parts that were fetched after all can be recorded separately.
+    pub fn abort(&mut self, key: &str) -> Option<TaggedInterval<Bound>> {
+        if !self.pending.contains_key(key) {
+            return None;
+        }
+        Arc::make_mut(&mut self.pending).remove(key)
+    }
+
+    /// Returns the entries recorded with a key, with their keys, in no
+    /// particular order.
+    pub fn keyed(&self) -> impl Iterator<Item = (&str, &TaggedInterval<Bound>)> {
+        self.keys
+            .iter()
+            .map(move |(key, i)| (key.as_str(), &self.history[*i]))
+    }
+
+    /// Removes the entry recorded under `key`, if any.
+    pub fn retract(&mut self, key: &str) -> Option<TaggedInterval<Bound>> {
+        let index = *self.keys.get(key)?;
+        self.notify(extent(&[&self.history[index]]), |set| {
+            let keys = Arc::make_mut(&mut set.keys);
+            let history = Arc::make_mut(&mut set.history);
+            let index = keys.remove(key)?;
+            let entry = history.swap_remove(index);
+            if index < history.len() {
+                let moved = history.len();
+                if let Some(i) = keys.values_mut().find(|i| **i == moved) {
+                    *i = index;
+                }
+            }
+            Some(entry)
+        })
+    }
+
+    /// Returns a receiver of the changes that later writes make to the
+    /// coverage of `range`. The watch ends once the receiver is dropped.
+    pub fn watch(&mut self, range: TaggedInterval<Bound>) -> Receiver<CoverageChange<Bound>> {
+        let (sender, receiver) = mpsc::channel();
+        self.watches.push(Watch { range, sender });
+        receiver
+    }
+
+    /// Returns a receiver of the tags of the goal named `name` whose
+    /// coverage becomes complete with later writes. The watch ends once the
+    /// receiver is dropped.
+    pub fn watch_completion(&mut self, name: &str) -> Receiver<String> {
+        let (sender, receiver) = mpsc::channel();
+        self.completions.push(Completion {
+            name: name.to_string(),
+            sender,
+        });
+        receiver
+    }
+
+    /// Applies `mutate` as `notify_watches` does, then sends each
+    /// completion watch the tags that its goal has newly completed. Only
+    /// goals overlapping `extent` are evaluated.
+    fn notify<F, R>(&mut self, extent: Option<TaggedInterval<Bound>>, mutate: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let before = self.completed(extent.as_ref());
+        let result = self.notify_watches(extent.clone(), mutate);
+        let after = self.completed(extent.as_ref());
+
+        let mut changes = before.into_iter().zip(after);
+        self.completions.retain(|completion| match changes.next() {
+            Some((Some(before), Some(after))) => after
+                .difference(&before)
+                .all(|tag| completion.sender.send(tag.clone()).is_ok()),
+            _ => true,
+        });
+        result
+    }
+
+    /// Returns the tags that the goal of each completion watch covers
+    /// completely, or `None` for goals that `extent` does not overlap.
+    fn completed(&self, extent: Option<&TaggedInterval<Bound>>) -> Vec<Option<HashSet<String>>> {
+        let extent = match extent {
+            Some(extent) => extent,
+            None => return vec![],
+        };
+        self.completions
+            .iter()
+            .map(|completion| {
+                let goal = self.goals.get(&completion.name)?;
+                overlap(goal, extent)?;
+                let missing = self.missing(&completion.name)?;
+                let mut tags = goal.tags.clone();
+                for iv in &missing {
+                    tags.retain(|tag| !iv.tags.contains(tag));
+                }
+                Some(tags)
+            })
+            .collect()
+    }
+
+    /// Applies `mutate`, which adds or removes entries within `extent`, and
+    /// sends each watch the change in its coverage, if any. Only watches
+    /// overlapping `extent` are evaluated, and only within it.
+    fn notify_watches<F, R>(&mut self, extent: Option<TaggedInterval<Bound>>, mutate: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let windows: Vec<_> = match &extent {
+            Some(extent) => self
+                .watches
+                .iter()
+                .map(|watch| overlap(&watch.range, extent))
+                .collect(),
+            None => vec![],
+        };
+        let extent = match extent {
+            Some(extent) if windows.iter().any(Option::is_some) => extent,
+            _ => return mutate(self),
+        };
+        let local = crate::clip_history(&extent, &self.history);
+        let before: Vec<_> = windows
+            .iter()
+            .map(|window| {
+                window
+                    .as_ref()
+                    .map(|w| w.clone().intersection(&local))
+                    .unwrap_or_default()
+            })
+            .collect();
+        let result = mutate(self);
+
+        let local = crate::clip_history(&extent, &self.history);
+        let mut windows = windows.into_iter().zip(before);
+        self.watches.retain(|watch| {
+            let (window, before) = match windows.next() {
+                Some((Some(window), before)) => (window, before),
+                _ => return true,
+            };
+            let after = window.intersection(&local);
+            let change = CoverageChange {
+                covered: uncovered_by(&after, &before),
+                uncovered: uncovered_by(&before, &after),
+            };
+            (change.covered.is_empty() && change.uncovered.is_empty())
+                || watch.sender.send(change).is_ok()
+        });
+        result
+    }
+
+    /// Returns a copy of the current state for reading, e.g. on another
+    /// thread, while this set keeps recording.
+    pub fn snapshot(&self) -> Self {
+        self.clone()
+    }
+
+    pub fn history(&self) -> &[TaggedInterval<Bound>] {
+        &self.history
+    }
+
+    /// Returns what the goal named `name` is missing.
+    pub fn missing(&self, name: &str) -> Option<Vec<TaggedInterval<Bound>>> {
+        let goal = self.goals.get(name)?;
+        let history = crate::clip_history(goal, &self.history);
+        Some(goal.clone().difference(history).into_vec())
+    }
+
+    /// Returns what the goal named `name` is missing, with the time each
+    /// part was first found missing as its meta. Parts missing at the
+    /// previous call keep the time found then; the rest are found at `now`.
+    pub fn missing_with_age(
+        &mut self,
+        name: &str,
+        now: Bound,
+    ) -> Option<Vec<TaggedInterval<Bound, Bound>>> {
+        let missing = flatten_per_tag(&self.missing(name)?);
+        let previous = self.ages.get(name);
+        let no_spans = vec![];
+
+        let mut ages = HashMap::new();
+        for (tag, spans) in missing {
+            let known = previous.and_then(|p| p.get(&tag)).unwrap_or(&no_spans);
+            let mut aged: Vec<(Bound, Bound, Bound)> = vec![];
+            let mut push = |lower: Bound, upper: Bound, age: &Bound| match aged.last_mut() {
+                Some(last) if last.1 == lower && last.2 == *age => last.1 = upper,
+                _ => aged.push((lower, upper, age.clone())),
+            };
+            for (lower, upper) in spans {
+                let mut at = lower.clone();
+                for (l, u, age) in known.iter().filter(|(l, u, _)| *l < upper && *u > lower) {
+                    if at < *l {
+                        push(at.clone(), l.clone(), &now);
+                    }
+                    let end = u.clone().min(upper.clone());
+                    push(at.max(l.clone()), end.clone(), age);
+                    at = end;
+                }
+                if at < upper {
+                    push(at, upper, &now);
+                }
+            }
+            ages.insert(tag, aged);
+        }
+
+        let result = with_ages(&ages);
+        Arc::make_mut(&mut self.ages).insert(name.to_string(), ages);
+        Some(result)
+    }
+
+    /// Returns the covered fraction of the goal named `name`, over all its
+    /// tags.
+    pub fn progress<D>(&self, name: &str) -> Option<f64>
+    where
+        Bound: Sub<Output = D>,
+        D: Length,
+    {
+        Some(progress(self.goals.get(name)?, &self.missing(name)?))
+    }
+
+    /// Returns the covered fraction of the goal named `name` for each of
+    /// its tags.
+    pub fn progress_by_tag<D>(&self, name: &str) -> Option<HashMap<String, f64>>
+    where
+        Bound: Sub<Output = D>,
+        D: Length,
+    {
+        Some(progress_by_tag(self.goals.get(name)?, &self.missing(name)?))
+    }
+
+    /// Returns the ranges missing for any goal, per tag, with the work
+    /// shared by overlapping goals counted once. Pending entries are left
+    /// out.
+    pub fn combined_missing(&self) -> HashMap<String, Vec<(Bound, Bound)>> {
+        let mut combined: HashMap<String, Timeline<Bound>> = HashMap::new();
+        for name in self.goals.keys() {
+            let missing = self.missing(name).unwrap_or_default();
+            for (tag, spans) in flatten_per_tag(&missing) {
+                let timeline = combined.entry(tag).or_default();
+                *timeline = timeline.union(&Timeline::from_spans(&spans));
+            }
+        }
+        let pending: Vec<_> = self.pending.values().cloned().collect();
+        let pending = Timeline::per_tag(&pending);
+        combined
+            .into_iter()
+            .map(|(tag, timeline)| match pending.get(&tag) {
+                Some(pending) => (tag, timeline.difference(pending).spans()),
+                None => (tag, timeline.spans()),
+            })
+            .filter(|(_, spans)| !spans.is_empty())
+            .collect()
+    }
+}
+
+/// Returns the spans in `ages` as intervals with their age as meta, split
+/// where the tags or the age change and ordered by lower bound.
+fn with_ages<Bound>(ages: &Ages<Bound>) -> Vec<TaggedInterval<Bound, Bound>>
+where
+    Bound: BoundOps,
+{
+    let bounds: Vec<&Bound> = ages
+        .values()
+        .flatten()
+        .flat_map(|(l, u, _)| vec![l, u])
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let mut result: Vec<TaggedInterval<Bound, Bound>> = vec![];
+    // The intervals ending at the current lower bound.
+    let mut open = vec![];
+    for pair in bounds.windows(2) {
+        let (lower, upper) = (pair[0], pair[1]);
+        let mut groups: BTreeMap<&Bound, HashSet<String>> = BTreeMap::new();
+        for (tag, spans) in ages {
+            if let Some((_, _, age)) = spans.iter().find(|(l, u, _)| l <= lower && upper <= u) {
+                groups.entry(age).or_default().insert(tag.clone());
+            }
+        }
+
+        let mut next = vec![];
+        for (age, tags) in groups {
+            match open
+                .iter()
+                .find(|i: &&usize| result[**i].tags == tags && result[**i].meta() == Some(age))
+            {
+                Some(i) => {
+                    result[*i].upper = upper.clone();
+                    next.push(*i);
+                }
+                None => {
+                    next.push(result.len());
+                    result.push(TaggedInterval::with_meta(
+                        lower.clone(),
+                        upper.clone(),
+                        tags,
+                        age.clone(),
+                    ));
+                }
+            }
+        }
+        open = next;
+    }
+    result
+}
+
+/// Returns the interval spanning `entries` with all of their tags.
+fn extent<Bound>(entries: &[&TaggedInterval<Bound>]) -> Option<TaggedInterval<Bound>>
+where
+    Bound: BoundOps,
+{
+    let lower = entries.iter().map(|iv| &iv.lower).min()?;
+    let upper = entries.iter().map(|iv| &iv.upper).max()?;
+    let tags = entries
+        .iter()
+        .flat_map(|iv| iv.tags.iter().cloned())
+        .collect();
+    Some(TaggedInterval::new(lower.clone(), upper.clone(), tags))
+}
+
+/// Returns the part of `range` inside `extent` with the tags of both, if
+/// it is not empty.
+fn overlap<Bound>(
+    range: &TaggedInterval<Bound>,
+    extent: &TaggedInterval<Bound>,
+) -> Option<TaggedInterval<Bound>>
+where
+    Bound: BoundOps,
+{
+    let lower = range.lower.clone().max(extent.lower.clone());
+    let upper = range.upper.clone().min(extent.upper.clone());
+    let tags: HashSet<String> = range.tags.intersection(&extent.tags).cloned().collect();
+    if lower >= upper || tags.is_empty() {
+        return None;
+    }
+    Some(TaggedInterval::new(lower, upper, tags))
+}
+
+/// Returns the parts of `coverage` that `other` does not cover.
+fn uncovered_by<Bound>(
+    coverage: &[TaggedInterval<Bound>],
+    other: &[TaggedInterval<Bound>],
+) -> Vec<TaggedInterval<Bound>>
+where
+    Bound: BoundOps,
+{
+    let parts = coverage
+        .iter()
+        .flat_map(|iv| iv.clone().difference(other.to_vec()).into_vec())
+        .collect();
+    TaggedInterval::union_all(parts)
+}
+
+/// Returns the fraction of `goal`, over all its tags, that `missing` does
+/// not cover.
+fn progress<Bound, D>(goal: &TaggedInterval<Bound>, missing: &[TaggedInterval<Bound>]) -> f64
+where
+    Bound: BoundOps + Sub<Output = D>,
+    D: Length,
+{
+    let length = |(l, u): &(Bound, Bound)| (u.clone() - l.clone()).as_f64();
+    let total = length(&(goal.lower.clone(), goal.upper.clone())) * goal.tags.len() as f64;
+    if total <= 0.0 {
+        return 1.0;
+    }
+    let missing = flatten_per_tag(missing)
+        .values()
+        .flatten()
+        .fold(0.0, |sum, span| sum + length(span));
+    (total - missing) / total
+}
+
+/// Returns the fraction of `goal` that `missing` does not cover, per tag.
+fn progress_by_tag<Bound, D>(
+    goal: &TaggedInterval<Bound>,
+    missing: &[TaggedInterval<Bound>],
+) -> HashMap<String, f64>
+where
+    Bound: BoundOps + Sub<Output = D>,
+    D: Length,
+{
+    goal.tags
+        .iter()
+        .map(|tag| {
+            let only = |iv: &TaggedInterval<Bound>| {
+                let tags = std::iter::once(tag.clone()).collect();
+                TaggedInterval::new(iv.lower.clone(), iv.upper.clone(), tags)
+            };
+            let missing: Vec<_> = missing
+                .iter()
+                .filter(|iv| iv.tags.contains(tag))
+                .map(only)
+                .collect();
+            (tag.clone(), progress(&only(goal), &missing))
+        })
+        .collect()
+}
+
+/// Goal sets over disjoint groups of tags. Each goal and entry is split by
+/// tag across the shards, so the shards can be mutated, or locked,
+/// separately; the methods here report over all of them.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShardedGoalSet<Bound>
+where
+    Bound: BoundOps,
+{
+    shards: Vec<GoalSet<Bound>>,
+    /// Tags placed explicitly rather than by hash.
+    assigned: HashMap<String, usize>,
+}
+
+impl<Bound> ShardedGoalSet<Bound>
+where
+    Bound: BoundOps,
+{
+    /// Panics if `shards` is zero.
+    pub fn new(shards: usize) -> Self {
+        assert!(shards > 0, "there must be at least one shard");
+        Self {
+            shards: vec![GoalSet::default(); shards],
+            assigned: HashMap::new(),
+        }
+    }
+
+    /// Places `tag` in `shard` instead of the shard its hash picks. Only
+    /// goals and entries added afterwards follow the assignment.
+    ///
+    /// Panics if `shard` is out of range.
+    pub fn assign(&mut self, tag: &str, shard: usize) {
+        assert!(shard < self.shards.len(), "shard out of range");
+        self.assigned.insert(tag.to_string(), shard);
+    }
+
+    /// Returns the shard that holds `tag`.
+    pub fn shard_of(&self, tag: &str) -> usize {
+        if let Some(shard) = self.assigned.get(tag) {
+            return *shard;
+        }
+        let mut hasher = DefaultHasher::new();
+        tag.hash(&mut hasher);
+        (hasher.finish() % self.shards.len() as u64) as usize
+    }
+
+    pub fn shards(&self) -> &[GoalSet<Bound>] {
+        &self.shards
+    }
+
+    /// Returns the shards for mutating separately. Entries recorded in a
+    /// shard directly should only carry tags that `shard_of` places there.
+    pub fn shards_mut(&mut self) -> &mut [GoalSet<Bound>] {
+        &mut self.shards
+    }
+
+    /// Splits `iv` into one interval per shard holding some of its tags.
+    fn split(&self, iv: TaggedInterval<Bound>) -> BTreeMap<usize, TaggedInterval<Bound>> {
+        let mut parts = BTreeMap::new();
+        for tag in &iv.tags {
+            parts
+                .entry(self.shard_of(tag))
+                .or_insert_with(|| {
+                    TaggedInterval::new(iv.lower.clone(), iv.upper.clone(), HashSet::new())
+                })
+                .tags
+                .insert(tag.clone());
+        }
+        parts
+    }
+
+    /// Adds or replaces the goal named `name`. A goal without tags is kept
+    /// in the first shard.
+    pub fn insert(&mut self, name: &str, goal: TaggedInterval<Bound>) {
+        let mut parts = self.split(goal.clone());
+        if parts.is_empty() {
+            parts.insert(0, goal);
+        }
+        for (i, shard) in self.shards.iter_mut().enumerate() {
+            match parts.remove(&i) {
+                Some(part) => shard.insert(name, part),
+                None => {
+                    shard.remove(name);
+                }
+            }
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<TaggedInterval<Bound>> {
+        self.shards
+            .iter_mut()
+            .filter_map(|shard| shard.remove(name))
+            .fold(None, |goal: Option<TaggedInterval<Bound>>, part| {
+                Some(match goal {
+                    Some(mut goal) => {
+                        goal.tags.extend(part.tags);
+                        goal
+                    }
+                    None => part,
+                })
+            })
+    }
+
+    /// Records `entry` and returns the parts of it that were not covered
+    /// before; see `GoalSet::record`.
+    pub fn record(&mut self, entry: TaggedInterval<Bound>) -> Vec<TaggedInterval<Bound>> {
+        let mut gained = vec![];
+        for (i, part) in self.split(entry) {
+            gained.extend(self.shards[i].record(part));
+        }
+        TaggedInterval::union_all(gained)
+    }
+
+    /// Records a batch of entries, compacting each shard once; see
+    /// `GoalSet::record_all`.
+    pub fn record_all<I>(&mut self, entries: I)
+    where
+        I: IntoIterator<Item = TaggedInterval<Bound>>,
+    {
+        let mut batches = vec![vec![]; self.shards.len()];
+        for entry in entries {
+            for (i, part) in self.split(entry) {
+                batches[i].push(part);
+            }
+        }
+        for (shard, batch) in self.shards.iter_mut().zip(batches) {
+            if !batch.is_empty() {
+                shard.record_all(batch);
+            }
+        }
+    }
+
+    /// Returns the goal named `name` with the tags of all shards.
+    pub fn goal(&self, name: &str) -> Option<TaggedInterval<Bound>> {
+        let mut parts = self.shards.iter().filter_map(|shard| shard.goals.get(name));
+        let mut goal = parts.next()?.clone();
+        for part in parts {
+            goal.tags.extend(part.tags.iter().cloned());
+        }
+        Some(goal)
+    }
+
+    /// Returns what the goal named `name` is missing over all shards.
+    pub fn missing(&self, name: &str) -> Option<Vec<TaggedInterval<Bound>>> {
+        let mut found = false;
+        let mut missing = vec![];
+        for shard in &self.shards {
+            if let Some(part) = shard.missing(name) {
+                found = true;
+                missing.extend(part);
+            }
+        }
+        if found {
+            Some(TaggedInterval::union_all(missing))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the covered fraction of the goal named `name`, over all its
+    /// tags.
+    pub fn progress<D>(&self, name: &str) -> Option<f64>
+    where
+        Bound: Sub<Output = D>,
+        D: Length,
+    {
+        Some(progress(&self.goal(name)?, &self.missing(name)?))
+    }
+
+    /// Returns the covered fraction of the goal named `name` for each of
+    /// its tags.
+    pub fn progress_by_tag<D>(&self, name: &str) -> Option<HashMap<String, f64>>
+    where
+        Bound: Sub<Output = D>,
+        D: Length,
+    {
+        Some(progress_by_tag(&self.goal(name)?, &self.missing(name)?))
+    }
+
+    /// Returns the ranges missing for any goal, per tag, as
+    /// `GoalSet::combined_missing` does.
+    pub fn combined_missing(&self) -> HashMap<String, Vec<(Bound, Bound)>> {
+        self.shards
+            .iter()
+            .flat_map(|shard| shard.combined_missing())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tags, tiv};
+    use chrono::{FixedOffset, Utc};
+
+    #[test]
+    fn resolve_works() {
+        let now = "2077-03-01T05:30:00+09:00"
+            .parse::<DateTime<FixedOffset>>()
+            .unwrap();
+        let time = |s: &str| s.parse::<DateTime<FixedOffset>>().unwrap();
+
+        let table = vec![
+            (
+                Window::Last(Duration::hours(48)),
+                ("2077-02-27T05:30:00+09:00", "2077-03-01T05:30:00+09:00"),
+            ),
+            (
+                Window::PreviousDay,
+                ("2077-02-28T00:00:00+09:00", "2077-03-01T00:00:00+09:00"),
+            ),
+            (
+                Window::PreviousMonth,
+                ("2077-02-01T00:00:00+09:00", "2077-03-01T00:00:00+09:00"),
+            ),
+        ];
+
+        for (window, (lower, upper)) in table {
+            let goal = Goal::new(window, tags(&["freedom"]));
+            assert_eq!(
+                goal.resolve(now),
+                Some(tiv(time(lower), time(upper), &["freedom"])),
+                "{:?}",
+                window
+            );
+        }
+
+        // In UTC it is still February.
+        let goal = Goal::new(Window::PreviousMonth, tags(&["freedom"]));
+        let utc = goal.resolve(now.with_timezone(&Utc)).unwrap();
+        assert_eq!(
+            (utc.lower, utc.upper),
+            (
+                "2077-01-01T00:00:00Z".parse().unwrap(),
+                "2077-02-01T00:00:00Z".parse().unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn goal_set_works() {
+        let mut goals = GoalSet::default();
+        goals.insert("morning", tiv(0i64, 50, &["freedom", "liberty"]));
+        goals.insert("overlap", tiv(25, 75, &["freedom"]));
+        goals.record_all(vec![
+            tiv(0, 30, &["freedom", "liberty"]),
+            tiv(20, 40, &["freedom", "liberty"]),
+        ]);
+        assert_eq!(goals.history(), [tiv(0, 40, &["freedom", "liberty"])]);
+
+        assert_eq!(
+            goals.missing("morning"),
+            Some(vec![tiv(40, 50, &["freedom", "liberty"])])
+        );
+        assert_eq!(goals.missing("evening"), None);
+        assert_eq!(goals.progress("morning"), Some(0.8));
+        assert_eq!(goals.progress("overlap"), Some(0.3));
+
+        let combined = goals.combined_missing();
+        assert_eq!(combined["freedom"], vec![(40, 75)]);
+        assert_eq!(combined["liberty"], vec![(40, 50)]);
+
+        goals.record(tiv(40, 45, &["freedom"]));
+        let by_tag = goals.progress_by_tag("morning").unwrap();
+        assert_eq!((by_tag["freedom"], by_tag["liberty"]), (0.9, 0.8));
+        assert_eq!(goals.progress_by_tag("evening"), None);
+    }
+
+    #[test]
+    fn record_keyed_works() {
+        let mut goals = GoalSet::default();
+        goals.insert("all", tiv(0i64, 100, &["freedom"]));
+        assert!(goals.record_keyed("a", tiv(0, 10, &["freedom"])));
+        assert!(goals.record_keyed("b", tiv(10, 20, &["freedom"])));
+        assert!(!goals.record_keyed("a", tiv(0, 10, &["freedom"])));
+        goals.record(tiv(30, 40, &["freedom"]));
+        assert!(goals.record_keyed("c", tiv(50, 60, &["freedom"])));
+        assert_eq!(goals.history().len(), 4);
+
+        // Compacting a batch keeps the keyed entries retractable.
+        goals.record_all(vec![tiv(35, 45, &["freedom"])]);
+        assert_eq!(goals.history().len(), 4);
+
+        let mut keyed: Vec<_> = goals.keyed().collect();
+        keyed.sort_by_key(|(key, _)| *key);
+        assert_eq!(
+            keyed,
+            vec![
+                ("a", &tiv(0, 10, &["freedom"])),
+                ("b", &tiv(10, 20, &["freedom"])),
+                ("c", &tiv(50, 60, &["freedom"])),
+            ]
+        );
+
+        assert_eq!(goals.retract("a"), Some(tiv(0, 10, &["freedom"])));
+        assert_eq!(goals.retract("a"), None);
+        assert_eq!(goals.retract("c"), Some(tiv(50, 60, &["freedom"])));
+        assert_eq!(goals.progress("all"), Some(0.25));
+    }
+
+    #[test]
+    fn pending_works() {
+        let mut goals = GoalSet::default();
+        goals.insert("all", tiv(0i64, 100, &["freedom", "liberty"]));
+        assert!(goals.begin("a", tiv(0, 40, &["freedom"])));
+        assert!(goals.begin("b", tiv(60, 100, &["freedom", "liberty"])));
+        assert!(!goals.begin("a", tiv(40, 60, &["freedom"])));
+
+        // Pending entries are still missing but are not planned again.
+        assert_eq!(goals.progress("all"), Some(0.0));
+        let combined = goals.combined_missing();
+        assert_eq!(combined["freedom"], vec![(40, 60)]);
+        assert_eq!(combined["liberty"], vec![(0, 60)]);
+
+        assert!(goals.commit("a"));
+        assert!(!goals.commit("a"));
+        assert!(!goals.begin("a", tiv(40, 60, &["freedom"])));
+        assert_eq!(
+            goals.abort("b"),
+            Some(tiv(60, 100, &["freedom", "liberty"]))
+        );
+        assert_eq!(goals.abort("b"), None);
+        assert_eq!(goals.progress("all"), Some(0.2));
+        let combined = goals.combined_missing();
+        assert_eq!(combined["freedom"], vec![(40, 100)]);
+        assert_eq!(combined["liberty"], vec![(0, 100)]);
+
+        // A committed entry can be retracted like any keyed one.
+        assert_eq!(goals.retract("a"), Some(tiv(0, 40, &["freedom"])));
+        assert_eq!(goals.progress("all"), Some(0.0));
+    }
+
+    #[test]
+    fn snapshot_works() {
+        let mut goals = GoalSet::default();
+        goals.insert("all", tiv(0i64, 100, &["freedom"]));
+        goals.record(tiv(0, 50, &["freedom"]));
+
+        let snapshot = goals.snapshot();
+        assert!(Arc::ptr_eq(&snapshot.history, &goals.history));
+
+        goals.record(tiv(50, 100, &["freedom"]));
+        goals.remove("all");
+        assert_eq!(
+            snapshot.missing("all"),
+            Some(vec![tiv(50, 100, &["freedom"])])
+        );
+        assert_eq!(snapshot.history().len(), 1);
+        assert_eq!(goals.missing("all"), None);
+        assert_eq!(goals.history().len(), 2);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn goal_set_serializes() {
+        let mut goals = GoalSet::default();
+        goals.insert("all", tiv(0i64, 100, &["freedom"]));
+        goals.record_all(vec![tiv(0, 30, &["freedom"]), tiv(20, 40, &["freedom"])]);
+        goals.record_keyed("a", tiv(50, 60, &["freedom"]));
+        goals.begin("b", tiv(60, 70, &["freedom"]));
+        goals.missing_with_age("all", 1);
+        let changes = goals.watch(tiv(0, 100, &["freedom"]));
+
+        let json = serde_json::to_string(&goals).unwrap();
+        let mut restored: GoalSet<i64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, goals);
+        assert!(restored.watches.is_empty());
+
+        assert!(restored.commit("b"));
+        assert_eq!(restored.retract("a"), Some(tiv(50, 60, &["freedom"])));
+        assert_eq!(restored.progress("all"), Some(0.5));
+        assert!(changes.try_recv().is_err());
+
+        let mut sharded = ShardedGoalSet::new(2);
+        sharded.insert("all", tiv(0i64, 100, &["freedom", "liberty"]));
+        sharded.record(tiv(0, 50, &["freedom"]));
+        let json = serde_json::to_string(&sharded).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ShardedGoalSet<i64>>(&json).unwrap(),
+            sharded
+        );
+    }
+
+    #[test]
+    fn watch_works() {
+        let mut goals = GoalSet::default();
+        goals.record_keyed("a", tiv(0i64, 20, &["freedom"]));
+        let changes = goals.watch(tiv(10, 50, &["freedom", "liberty"]));
+        let ignored = goals.watch(tiv(60, 70, &["freedom"]));
+        let other_tags = goals.watch(tiv(0, 100, &["fairness"]));
+        drop(goals.watch(tiv(0, 100, &["freedom"])));
+
+        goals.record(tiv(15, 30, &["freedom", "liberty"]));
+        assert_eq!(
+            changes.try_recv(),
+            Ok(CoverageChange {
+                covered: vec![
+                    tiv(15, 20, &["liberty"]),
+                    tiv(20, 30, &["freedom", "liberty"]),
+                ],
+                uncovered: vec![],
+            })
+        );
+        assert_eq!(goals.watches.len(), 3);
+
+        goals.retract("a");
+        assert_eq!(
+            changes.try_recv(),
+            Ok(CoverageChange {
+                covered: vec![],
+                uncovered: vec![tiv(10, 15, &["freedom"])],
+            })
+        );
+        assert!(changes.try_recv().is_err());
+        assert!(ignored.try_recv().is_err());
+        assert!(other_tags.try_recv().is_err());
+
+        // A batch is reported as one change.
+        goals.record_all(vec![tiv(5, 12, &["freedom"]), tiv(40, 65, &["freedom"])]);
+        assert_eq!(
+            changes.try_recv(),
+            Ok(CoverageChange {
+                covered: vec![tiv(10, 12, &["freedom"]), tiv(40, 50, &["freedom"])],
+                uncovered: vec![],
+            })
+        );
+        assert_eq!(
+            ignored.try_recv(),
+            Ok(CoverageChange {
+                covered: vec![tiv(60, 65, &["freedom"])],
+                uncovered: vec![],
+            })
+        );
+
+        // Snapshots do not carry the watches.
+        let mut snapshot = goals.snapshot();
+        snapshot.record(tiv(0, 100, &["freedom"]));
+        assert!(changes.try_recv().is_err());
+    }
+
+    #[test]
+    fn watch_completion_works() {
+        let mut goals = GoalSet::default();
+        goals.insert("all", tiv(0i64, 100, &["freedom", "liberty"]));
+        goals.record(tiv(0, 50, &["freedom", "liberty"]));
+        let completed = goals.watch_completion("all");
+        let unknown = goals.watch_completion("evening");
+
+        goals.record(tiv(50, 90, &["freedom", "liberty"]));
+        assert!(completed.try_recv().is_err());
+        goals.record_keyed("a", tiv(90, 100, &["freedom"]));
+        assert_eq!(completed.try_recv(), Ok("freedom".to_string()));
+
+        // A tag is reported again once it completes again.
+        goals.retract("a");
+        goals.record(tiv(90, 120, &["freedom", "liberty"]));
+        let mut tags = completed.try_iter().collect::<Vec<_>>();
+        tags.sort();
+        assert_eq!(tags, vec!["freedom", "liberty"]);
+
+        // Writes outside the goal are not evaluated.
+        goals.record(tiv(100, 150, &["freedom"]));
+        assert!(completed.try_recv().is_err());
+        assert!(unknown.try_recv().is_err());
+
+        drop(completed);
+        goals.insert("all", tiv(0, 200, &["freedom"]));
+        goals.record(tiv(150, 200, &["freedom"]));
+        assert_eq!(goals.completions.len(), 1);
+    }
+
+    #[test]
+    fn missing_with_age_works() {
+        let mut goals = GoalSet::default();
+        goals.insert("all", tiv(0i64, 100, &["freedom", "liberty"]));
+        goals.record_keyed("a", tiv(0, 50, &["freedom"]));
+
+        assert_eq!(
+            goals.missing_with_age("all", 1),
+            Some(vec![
+                TaggedInterval::with_meta(0, 50, tags(&["liberty"]), 1),
+                TaggedInterval::with_meta(50, 100, tags(&["freedom", "liberty"]), 1),
+            ])
+        );
+
+        goals.retract("a");
+        goals.record(tiv(70, 100, &["freedom", "liberty"]));
+        assert_eq!(
+            goals.missing_with_age("all", 5),
+            Some(vec![
+                TaggedInterval::with_meta(0, 50, tags(&["liberty"]), 1),
+                TaggedInterval::with_meta(0, 50, tags(&["freedom"]), 5),
+                TaggedInterval::with_meta(50, 70, tags(&["freedom", "liberty"]), 1),
+            ])
+        );
+        assert_eq!(goals.missing_with_age("evening", 5), None);
+
+        // Removing the goal forgets its ages.
+        let goal = goals.remove("all").unwrap();
+        goals.insert("all", goal);
+        assert_eq!(
+            goals.missing_with_age("all", 9),
+            Some(vec![TaggedInterval::with_meta(
+                0,
+                70,
+                tags(&["freedom", "liberty"]),
+                9
+            )])
+        );
+    }
+
+    #[test]
+    fn sharded_goal_set_works() {
+        let mut sharded = ShardedGoalSet::new(3);
+        sharded.assign("freedom", 0);
+        sharded.assign("liberty", 1);
+        sharded.assign("equality", 1);
+
+        let mut single = GoalSet::default();
+        let goals = vec![
+            (
+                "morning",
+                tiv(0i64, 50, &["freedom", "liberty", "equality"]),
+            ),
+            ("overlap", tiv(25, 75, &["freedom"])),
+            ("nothing", tiv(0, 10, &[])),
+        ];
+        let history = vec![
+            tiv(0, 30, &["freedom", "liberty"]),
+            tiv(20, 40, &["freedom", "liberty", "equality"]),
+        ];
+        for (name, goal) in goals {
+            sharded.insert(name, goal.clone());
+            single.insert(name, goal);
+        }
+        sharded.record_all(history.clone());
+        single.record_all(history);
+
+        assert_eq!(sharded.shards()[0].history(), [tiv(0, 40, &["freedom"])]);
+        assert_eq!(sharded.shards()[1].history().len(), 2);
+        assert!(sharded.shards()[2].history().is_empty());
+        for name in &["morning", "overlap", "nothing", "evening"] {
+            assert_eq!(sharded.missing(name), single.missing(name), "{}", name);
+            assert_eq!(sharded.progress(name), single.progress(name), "{}", name);
+            assert_eq!(
+                sharded.progress_by_tag(name),
+                single.progress_by_tag(name),
+                "{}",
+                name
+            );
+        }
+        assert_eq!(sharded.combined_missing(), single.combined_missing());
+
+        // Recording returns what was not covered before.
+        let entry = tiv(10, 50, &["freedom", "equality"]);
+        let gained = vec![
+            tiv(10, 20, &["equality"]),
+            tiv(40, 50, &["freedom", "equality"]),
+        ];
+        assert_eq!(single.record(entry.clone()), gained);
+        assert_eq!(sharded.record(entry), gained);
+
+        // Replacing a goal drops it from shards that no longer hold its tags.
+        sharded.insert("morning", tiv(0, 50, &["liberty"]));
+        assert_eq!(sharded.shards()[0].missing("morning"), None);
+        assert_eq!(sharded.remove("morning"), Some(tiv(0, 50, &["liberty"])));
+        assert_eq!(sharded.missing("morning"), None);
+    }
+}
@@ -0,0 +1,97 @@
+//! Helpers for Kafka consumption coverage. Offsets are `u64` bounds and each
+//! partition is a tag named like `orders-3`. Where one bound must order
+//! across partitions, `(partition, offset)` tuples work as bounds as well.
+
+use crate::TaggedInterval;
+use std::collections::{BTreeMap, HashSet};
+
+/// Returns the tag of `partition` of `topic`, e.g. `orders-3`.
+pub fn partition_tag(topic: &str, partition: u32) -> String {
+    format!("{}-{}", topic, partition)
+}
+
+fn single(tag: String) -> HashSet<String> {
+    [tag].iter().cloned().collect()
+}
+
+/// Turns consumer-group offset commits `(topic, partition, offset)`, in the
+/// order they were made, into consumed offset ranges. A commit advancing a
+/// partition covers the offsets since its previous commit; the first commit
+/// of a partition and commits moving it back only set the position.
+pub fn from_commits<'a, I>(commits: I) -> Vec<TaggedInterval<u64>>
+where
+    I: IntoIterator<Item = (&'a str, u32, u64)>,
+{
+    let mut positions: BTreeMap<String, u64> = BTreeMap::new();
+    let mut result = vec![];
+    for (topic, partition, offset) in commits {
+        let tag = partition_tag(topic, partition);
+        if let Some(&last) = positions.get(&tag) {
+            if last < offset {
+                result.push(TaggedInterval::new(last, offset, single(tag.clone())));
+            }
+        }
+        positions.insert(tag, offset);
+    }
+    result
+}
+
+/// Returns the offset ranges of each partition's log `(topic, partition,
+/// start, end)` not covered by `history`, ordered by partition and offset.
+pub fn unconsumed<'a, I>(logs: I, history: &[TaggedInterval<u64>]) -> Vec<TaggedInterval<u64>>
+where
+    I: IntoIterator<Item = (&'a str, u32, u64, u64)>,
+{
+    let mut logs: Vec<(String, u64, u64)> = logs
+        .into_iter()
+        .map(|(topic, partition, start, end)| (partition_tag(topic, partition), start, end))
+        .collect();
+    logs.sort();
+    logs.into_iter()
+        .flat_map(|(tag, start, end)| {
+            let history = history
+                .iter()
+                .filter(|iv| iv.tags.contains(&tag))
+                .cloned()
+                .collect();
+            TaggedInterval::new(start, end, single(tag)).difference(history)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiv;
+
+    #[test]
+    fn kafka_works() {
+        let commits = vec![
+            ("orders", 0, 100),
+            ("orders", 1, 0),
+            ("orders", 0, 150),
+            ("orders", 1, 40),
+            ("orders", 0, 120),
+            ("orders", 0, 180),
+        ];
+        let history = from_commits(commits);
+        assert_eq!(
+            history,
+            vec![
+                tiv(100, 150, &["orders-0"]),
+                tiv(0, 40, &["orders-1"]),
+                tiv(120, 180, &["orders-0"]),
+            ]
+        );
+
+        let logs = vec![("orders", 1, 0, 60), ("orders", 0, 0, 200)];
+        assert_eq!(
+            unconsumed(logs, &history),
+            vec![
+                tiv(0, 100, &["orders-0"]),
+                tiv(180, 200, &["orders-0"]),
+                tiv(40, 60, &["orders-1"]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{BoundOps, TaggedInterval};
+
+fn covers<Bound, M>(
+    intervals: &[TaggedInterval<Bound, M>],
+    tag: &str,
+    lower: Bound,
+    upper: Bound,
+) -> bool
+where
+    Bound: BoundOps,
+{
+    let mut spans: Vec<(Bound, Bound)> = intervals
+        .iter()
+        .filter(|iv| iv.tags.contains(tag))
+        .map(|iv| (iv.lower.clone(), iv.upper.clone()))
+        .collect();
+    spans.sort();
+
+    let mut reached = lower;
+    for (l, u) in spans {
+        if reached >= upper {
+            break;
+        }
+        if l <= reached && reached < u {
+            reached = u;
+        }
+    }
+    reached >= upper
+}
+
+/// Asserts that every output interval lies within `specified`, carries only
+/// specified tags, and that the output is sorted and non-overlapping.
+pub fn assert_within_specified<Bound, M, F, R>(
+    difference: F,
+    specified: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+) where
+    Bound: BoundOps,
+    M: Clone,
+    F: Fn(TaggedInterval<Bound, M>, Vec<TaggedInterval<Bound, M>>) -> R,
+    R: Into<Vec<TaggedInterval<Bound, M>>>,
+{
+    let result: Vec<_> = difference(specified.clone(), history.to_vec()).into();
+    for iv in result.iter() {
+        assert!(
+            specified.lower <= iv.lower && iv.lower < iv.upper && iv.upper <= specified.upper,
+            "{:?} is outside of {:?}..{:?}",
+            (iv.lower.clone(), iv.upper.clone()),
+            specified.lower,
+            specified.upper
+        );
+        assert!(
+            !iv.tags.is_empty() && iv.tags.is_subset(&specified.tags),
+            "{:?} is not a non-empty subset of {:?}",
+            iv.tags,
+            specified.tags
+        );
+    }
+    for pair in result.windows(2) {
+        assert!(
+            pair[0].upper <= pair[1].lower,
+            "{:?} overlaps {:?}",
+            (pair[0].lower.clone(), pair[0].upper.clone()),
+            (pair[1].lower.clone(), pair[1].upper.clone())
+        );
+    }
+}
+
+/// Asserts that nothing is missing once `specified` itself is part of the
+/// history.
+pub fn assert_empty_after_union<Bound, M, F, R>(
+    difference: F,
+    specified: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+) where
+    Bound: BoundOps,
+    M: Clone,
+    F: Fn(TaggedInterval<Bound, M>, Vec<TaggedInterval<Bound, M>>) -> R,
+    R: Into<Vec<TaggedInterval<Bound, M>>>,
+{
+    let mut history = history.to_vec();
+    history.push(specified.clone());
+    let result: Vec<_> = difference(specified.clone(), history).into();
+    assert!(
+        result.is_empty(),
+        "{:?} is missing despite being in the history",
+        result
+            .iter()
+            .map(|iv| (iv.lower.clone(), iv.upper.clone()))
+            .collect::<Vec<_>>()
+    );
+}
+
+/// Asserts that adding `extra` to the history never makes anything missing
+/// that was covered before.
+pub fn assert_monotone<Bound, M, F, R>(
+    difference: F,
+    specified: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+    extra: &[TaggedInterval<Bound, M>],
+) where
+    Bound: BoundOps,
+    M: Clone,
+    F: Fn(TaggedInterval<Bound, M>, Vec<TaggedInterval<Bound, M>>) -> R,
+    R: Into<Vec<TaggedInterval<Bound, M>>>,
+{
+    let before: Vec<_> = difference(specified.clone(), history.to_vec()).into();
+    let after: Vec<_> = difference(specified.clone(), [history, extra].concat()).into();
+    for iv in after.iter() {
+        for tag in iv.tags.iter() {
+            assert!(
+                covers(&before, tag, iv.lower.clone(), iv.upper.clone()),
+                "{:?} of {} became missing after extending the history",
+                (iv.lower.clone(), iv.upper.clone()),
+                tag
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiv;
+
+    #[test]
+    fn difference_obeys_laws() {
+        let specified = tiv(0, 100, &["freedom", "liberty", "fairness"]);
+        let history = vec![
+            tiv(-10, 20, &["freedom"]),
+            tiv(10, 40, &["liberty", "fairness"]),
+            tiv(60, 120, &["freedom", "liberty"]),
+        ];
+        let extra = vec![tiv(30, 70, &["fairness"]), tiv(90, 95, &["liberty"])];
+
+        assert_within_specified(TaggedInterval::difference, &specified, &history);
+        assert_empty_after_union(TaggedInterval::difference, &specified, &history);
+        assert_monotone(TaggedInterval::difference, &specified, &history, &extra);
+    }
+}
@@ -1,114 +1,374 @@
-use std::collections::{HashMap, HashSet};
-use std::fmt::Debug;
-use std::iter::FromIterator;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
+use std::hash::Hash;
 use std::marker::Sized;
+use std::ops::{Add, ControlFlow, Sub};
 
-pub trait BoundOps: Copy + Debug + Eq + Ord
+pub mod analysis;
+mod bitset;
+mod boundary_map;
+pub mod composite;
+pub mod coverage;
+pub mod difference;
+pub mod goal;
+pub mod kafka;
+#[cfg(feature = "test-utils")]
+pub mod laws;
+pub mod manifest;
+#[cfg(feature = "object-store")]
+pub mod object_store;
+mod parallel;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+pub mod query;
+pub mod range_query;
+pub mod render;
+pub mod report;
+pub mod schedule;
+pub mod sequence;
+pub mod sketch;
+pub mod slo;
+pub mod tag_set;
+pub mod timeline;
+pub mod wire;
+#[cfg(feature = "bench")]
+pub mod workload;
+
+pub use coverage::Coverage;
+pub use difference::Difference;
+
+pub trait BoundOps: Clone + Debug + Eq + Ord
 where
     Self: Sized,
 {
 }
-impl<T> BoundOps for T where T: Copy + Debug + Eq + Ord {}
+impl<T> BoundOps for T where T: Clone + Debug + Eq + Ord {}
+
+/// The distance between two bounds, as needed for ratios and reports.
+pub trait Length: Copy + Debug + Ord + Add<Output = Self> + Default {
+    fn as_f64(self) -> f64;
+}
+
+impl Length for chrono::Duration {
+    fn as_f64(self) -> f64 {
+        self.as_seconds_f64()
+    }
+}
+
+macro_rules! impl_length {
+    ($($t:ty),*) => {
+        $(impl Length for $t {
+            fn as_f64(self) -> f64 {
+                self as f64
+            }
+        })*
+    };
+}
+
+impl_length!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// Rounding of a bound to a multiple of a resolution.
+pub trait Snap<D>: Sized {
+    fn snap_down(&self, resolution: D) -> Self;
+    fn snap_up(&self, resolution: D) -> Self;
+}
+
+impl<Tz> Snap<chrono::Duration> for chrono::DateTime<Tz>
+where
+    Tz: chrono::TimeZone,
+{
+    /// Panics if the resolution is not positive or out of range.
+    fn snap_down(&self, resolution: chrono::Duration) -> Self {
+        use chrono::DurationRound;
+        self.clone()
+            .duration_trunc(resolution)
+            .expect("resolution out of range")
+    }
+
+    fn snap_up(&self, resolution: chrono::Duration) -> Self {
+        let down = self.snap_down(resolution);
+        if down == *self {
+            down
+        } else {
+            down + resolution
+        }
+    }
+}
+
+macro_rules! impl_snap {
+    ($($t:ty),*) => {
+        $(impl Snap<$t> for $t {
+            fn snap_down(&self, resolution: $t) -> Self {
+                self.div_euclid(resolution) * resolution
+            }
+
+            fn snap_up(&self, resolution: $t) -> Self {
+                let down = self.snap_down(resolution);
+                if down == *self {
+                    down
+                } else {
+                    down + resolution
+                }
+            }
+        })*
+    };
+}
+
+impl_snap!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// The next bound of a discrete bound type, so that a single point can be
+/// turned into the half-open range `point..point.successor()`. Returns
+/// `None` for the largest bound, which no half-open range can contain.
+pub trait Successor: Sized {
+    fn successor(&self) -> Option<Self>;
+}
+
+/// The next representable instant, one nanosecond later.
+impl<Tz> Successor for chrono::DateTime<Tz>
+where
+    Tz: chrono::TimeZone,
+{
+    fn successor(&self) -> Option<Self> {
+        self.clone()
+            .checked_add_signed(chrono::Duration::nanoseconds(1))
+    }
+}
+
+macro_rules! impl_successor {
+    ($($t:ty),*) => {
+        $(impl Successor for $t {
+            fn successor(&self) -> Option<Self> {
+                self.checked_add(1)
+            }
+        })*
+    };
+}
+
+impl_successor!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct TaggedInterval<Bound>
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TaggedInterval<Bound, M = ()>
 where
     Bound: BoundOps,
 {
     lower: Bound,
     upper: Bound,
     tags: HashSet<String>,
+    meta: Option<M>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum BoundKind {
-    Specified,
-    History,
+pub trait OutputTags<Bound, M = ()>
+where
+    Bound: BoundOps,
+{
+    /// Returns the tags of `specified` to report for a range spanned by the
+    /// `covering` history entries.
+    fn output_tags(
+        &self,
+        specified: &TaggedInterval<Bound, M>,
+        covering: &[&TaggedInterval<Bound, M>],
+    ) -> HashSet<String>;
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
-enum BoundDirection {
-    Lower,
-    Upper,
+pub struct Uncovered;
+
+impl<Bound, M> OutputTags<Bound, M> for Uncovered
+where
+    Bound: BoundOps,
+{
+    fn output_tags(
+        &self,
+        specified: &TaggedInterval<Bound, M>,
+        covering: &[&TaggedInterval<Bound, M>],
+    ) -> HashSet<String> {
+        let covered: HashSet<&String> = covering.iter().flat_map(|iv| iv.tags.iter()).collect();
+        specified
+            .tags
+            .iter()
+            .filter(|t| !covered.contains(t))
+            .cloned()
+            .collect()
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-struct TaggedBound<Bound>
+pub trait CoverageSource<Bound, M = ()>
 where
     Bound: BoundOps,
 {
-    kind: BoundKind,
-    direction: BoundDirection,
-    bound: Bound,
-    tags: HashSet<String>,
+    /// Returns history entries covering any of `tags` between `lower` and
+    /// `upper`. Entries may extend beyond the window.
+    fn covered(
+        &self,
+        lower: Bound,
+        upper: Bound,
+        tags: &HashSet<String>,
+    ) -> Vec<TaggedInterval<Bound, M>>;
 }
 
-impl<Bound> TaggedBound<Bound>
+impl<Bound, M> CoverageSource<Bound, M> for [TaggedInterval<Bound, M>]
 where
     Bound: BoundOps,
+    M: Clone,
 {
-    fn from_interval(interval: &TaggedInterval<Bound>, kind: BoundKind) -> (Self, Self) {
-        (
-            Self {
-                kind,
-                direction: BoundDirection::Lower,
-                bound: interval.lower,
-                tags: interval.tags.clone(),
-            },
-            Self {
-                kind,
-                direction: BoundDirection::Upper,
-                bound: interval.upper,
-                tags: interval.tags.clone(),
-            },
-        )
+    fn covered(
+        &self,
+        lower: Bound,
+        upper: Bound,
+        tags: &HashSet<String>,
+    ) -> Vec<TaggedInterval<Bound, M>> {
+        self.iter()
+            .filter(|iv| iv.lower < upper && lower < iv.upper && !iv.tags.is_disjoint(tags))
+            .cloned()
+            .collect()
     }
+}
 
-    fn from_history(intervals: &Vec<TaggedInterval<Bound>>) -> Vec<Self> {
-        let mut bounds = vec![];
-        for iv in intervals {
-            let (lower, upper) = Self::from_interval(iv, BoundKind::History);
-            bounds.push(lower);
-            bounds.push(upper);
-        }
-        bounds
+impl<Bound, M> CoverageSource<Bound, M> for Vec<TaggedInterval<Bound, M>>
+where
+    Bound: BoundOps,
+    M: Clone,
+{
+    fn covered(
+        &self,
+        lower: Bound,
+        upper: Bound,
+        tags: &HashSet<String>,
+    ) -> Vec<TaggedInterval<Bound, M>> {
+        self.as_slice().covered(lower, upper, tags)
     }
+}
 
-    fn from_specified(interval: &TaggedInterval<Bound>) -> Vec<Self> {
-        let mut bounds = vec![];
-        let (lower, upper) = Self::from_interval(interval, BoundKind::Specified);
-        bounds.push(lower);
-        bounds.push(upper);
-        bounds
-    }
+#[derive(Clone, Debug, PartialEq)]
+pub enum CoverageAnswer<Bound> {
+    Covered,
+    Uncovered,
+    /// Ask again for each part of the range split at these points.
+    SplitAt(Vec<Bound>),
+}
 
-    pub fn from_intervals(
-        specified: &TaggedInterval<Bound>,
-        history: &Vec<TaggedInterval<Bound>>,
-    ) -> Vec<Self> {
-        vec![Self::from_specified(specified), Self::from_history(history)].concat()
+/// A virtual history answering, for a range and a tag, whether it is
+/// covered. Splits that do not fall strictly inside the range are treated as
+/// uncovered.
+#[derive(Clone, Copy, Debug)]
+pub struct Predicate<F>(pub F);
+
+impl<Bound, M, F> CoverageSource<Bound, M> for Predicate<F>
+where
+    Bound: BoundOps,
+    F: Fn(&Bound, &Bound, &str) -> CoverageAnswer<Bound>,
+{
+    fn covered(
+        &self,
+        lower: Bound,
+        upper: Bound,
+        tags: &HashSet<String>,
+    ) -> Vec<TaggedInterval<Bound, M>> {
+        let mut result = vec![];
+        for tag in tags {
+            let mut ranges = vec![(lower.clone(), upper.clone())];
+            while let Some((l, u)) = ranges.pop() {
+                match (self.0)(&l, &u, tag) {
+                    CoverageAnswer::Covered => result.push(TaggedInterval {
+                        lower: l,
+                        upper: u,
+                        tags: [tag.clone()].iter().cloned().collect(),
+                        meta: None,
+                    }),
+                    CoverageAnswer::Uncovered => {}
+                    CoverageAnswer::SplitAt(mut points) => {
+                        points.retain(|p| l < *p && *p < u);
+                        points.sort();
+                        points.dedup();
+                        if points.is_empty() {
+                            continue;
+                        }
+                        points.insert(0, l);
+                        points.push(u);
+                        ranges.extend(points.windows(2).map(|w| (w[0].clone(), w[1].clone())));
+                    }
+                }
+            }
+        }
+        result
     }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rounding {
+    /// Shrink entries to the grid, never claiming coverage that was not there.
+    Inward,
+    /// Grow entries to the grid, never losing coverage that was there.
+    Outward,
+}
+
+/// How the sweep treats history entries that touch, i.e. where one ends at
+/// the bound another starts. Either way the same tags are covered on both
+/// sides of the bound, so the choice only decides whether the output is
+/// split there.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TieBreak {
+    /// Leave the entries ending at a bound before entering the ones starting
+    /// there, so touching entries join without a seam.
+    #[default]
+    UpperFirst,
+    /// Enter the entries starting at a bound before leaving the ones ending
+    /// there, and split the output where touching entries share a specified
+    /// tag, so that the seam between them shows.
+    LowerFirst,
+}
+
+/// What a zero-length history entry means.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ZeroLength {
+    /// It covers nothing, as in `difference`.
+    #[default]
+    Ignore,
+    /// It covers its single point, as in `difference_with_points`.
+    CoverPoint,
+    /// It is rejected, as in `try_difference`.
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZeroLengthError<Bound> {
+    /// Position of the entry in the history.
+    pub index: usize,
+    pub bound: Bound,
+}
 
-    pub fn sort(bounds: &mut Vec<Self>) -> &mut Vec<Self> {
-        bounds.sort_by(|x, y| x.bound.partial_cmp(&y.bound).unwrap());
-        bounds
+impl<Bound> Display for ZeroLengthError<Bound>
+where
+    Bound: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "history entry {} has zero length at {:?}",
+            self.index, self.bound
+        )
     }
 }
 
-fn difference_with_dups(v1: &Vec<String>, v2: &Vec<String>) -> Vec<String> {
-    let mut result = v1.clone();
-    let mut counts: HashMap<String, i128> = HashMap::new();
-    v2.iter().for_each(|s| {
-        counts.entry(s.clone()).and_modify(|n| *n += 1).or_insert(1);
-    });
-    result.retain(|s| {
-        *counts
-            .entry(s.clone())
-            .and_modify(|n| *n -= 1)
-            .or_insert(-1)
-            < 0
-    });
-    result
+impl<Bound> Error for ZeroLengthError<Bound> where Bound: Debug {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfidenceAggregate {
+    Max,
+    Sum,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HistoryEvent<Bound, M = ()>
+where
+    Bound: BoundOps,
+{
+    Cover(TaggedInterval<Bound, M>),
+    Revoke(TaggedInterval<Bound, M>),
 }
 
 impl<Bound> TaggedInterval<Bound>
@@ -116,138 +376,962 @@ where
     Bound: BoundOps,
 {
     pub fn new(lower: Bound, upper: Bound, tags: HashSet<String>) -> Self {
-        Self { lower, upper, tags }
+        Self {
+            lower,
+            upper,
+            tags,
+            meta: None,
+        }
+    }
+}
+
+impl<Bound, M> TaggedInterval<Bound, M>
+where
+    Bound: BoundOps,
+    M: Clone + PartialEq,
+{
+    pub fn with_meta(lower: Bound, upper: Bound, tags: HashSet<String>, meta: M) -> Self {
+        Self {
+            lower,
+            upper,
+            tags,
+            meta: Some(meta),
+        }
     }
 
-    pub fn difference(self, history: Vec<Self>) -> Vec<Self> {
-        let mut bounds = TaggedBound::from_intervals(&self, &history);
-        TaggedBound::sort(&mut bounds);
+    pub fn meta(&self) -> Option<&M> {
+        self.meta.as_ref()
+    }
 
+    /// Returns the sub-ranges of `self` with the specified tags that
+    /// `history` does not cover. Zero-length history entries cover nothing.
+    pub fn difference(self, history: Vec<Self>) -> Difference<Bound, M> {
         let mut result = vec![];
-        let mut in_specified_range = false;
-        let mut current_tags = vec![];
-        let mut current_bound = self.lower;
-        let num_bounds = bounds.len();
-        let mut i = 0;
-
-        while i < num_bounds {
-            let mut specified_lower_found = false;
-            let mut specified_range_will_be_over = false;
-            let mut lower_tags = vec![];
-            let mut upper_tags = vec![];
-            let mut j = i;
-
-            while j < num_bounds && bounds[j].bound.eq(&bounds[i].bound) {
-                match bounds[j].kind {
-                    BoundKind::History => match bounds[j].direction {
-                        BoundDirection::Lower => {
-                            bounds[j]
-                                .tags
-                                .iter()
-                                .for_each(|t| lower_tags.push(t.clone()));
-                        }
-                        BoundDirection::Upper => {
-                            bounds[j]
-                                .tags
-                                .iter()
-                                .for_each(|t| upper_tags.push(t.clone()));
-                        }
-                    },
-                    BoundKind::Specified => match bounds[j].direction {
-                        BoundDirection::Lower => {
-                            specified_lower_found = true;
-                        }
-                        BoundDirection::Upper => {
-                            specified_range_will_be_over = true;
-                        }
-                    },
-                }
-                j += 1;
+        let _ = coverage::union_fitting(&self, &history, |iv| {
+            result.push(iv);
+            ControlFlow::Continue(())
+        });
+        result.into()
+    }
+
+    /// Like `difference`, but passes each output interval to `f` as soon as it
+    /// is found instead of collecting them, stopping early when `f` breaks.
+    pub fn difference_with<F>(self, history: Vec<Self>, f: F)
+    where
+        F: FnMut(Self) -> ControlFlow<()>,
+    {
+        let _ = coverage::union_fitting(&self, &history, f);
+    }
+
+    /// Like `difference`, but the sweep keeps the covered tags in `S`.
+    /// Returns `None` if there are more distinct tags than `S` can hold.
+    pub fn difference_with_tag_set<S>(self, history: Vec<Self>) -> Option<Difference<Bound, M>>
+    where
+        S: tag_set::TagSet,
+    {
+        coverage::union_with::<S, _, _>(&self, &history).map(Difference::from)
+    }
+
+    /// Like `difference`, but the output is split where touching history
+    /// entries meet if `tie_break` asks for it.
+    pub fn difference_with_tie_break(
+        self,
+        history: Vec<Self>,
+        tie_break: TieBreak,
+    ) -> Difference<Bound, M> {
+        coverage::sweep_with_tie_break(&self, &history, &coverage::Union, tie_break).into()
+    }
+
+    /// Like `difference`, but a zero-length history entry covers its single
+    /// point. A point at the largest bound lies outside every window and
+    /// covers nothing.
+    pub fn difference_with_points(self, history: Vec<Self>) -> Difference<Bound, M>
+    where
+        Bound: Successor,
+    {
+        let history = history
+            .into_iter()
+            .map(|iv| match iv.lower.successor() {
+                Some(upper) if iv.lower == iv.upper => Self { upper, ..iv },
+                _ => iv,
+            })
+            .collect();
+        self.difference(history)
+    }
+
+    /// Like `difference`, but zero-length history entries are rejected.
+    pub fn try_difference(
+        self,
+        history: Vec<Self>,
+    ) -> Result<Difference<Bound, M>, ZeroLengthError<Bound>> {
+        validate_zero_length(&history, ZeroLength::Error)?;
+        Ok(self.difference(history))
+    }
+
+    /// Like `difference`, but zero-length history entries are treated
+    /// according to `policy`. Bounds without a successor can use
+    /// `try_difference` for `ZeroLength::Error`.
+    pub fn difference_with_zero_length(
+        self,
+        history: Vec<Self>,
+        policy: ZeroLength,
+    ) -> Result<Difference<Bound, M>, ZeroLengthError<Bound>>
+    where
+        Bound: Successor,
+    {
+        match policy {
+            ZeroLength::Ignore => Ok(self.difference(history)),
+            ZeroLength::CoverPoint => Ok(self.difference_with_points(history)),
+            ZeroLength::Error => self.try_difference(history),
+        }
+    }
+
+    /// Like `difference`, but bounds are sorted and compared by `key`, which
+    /// must be injective and preserve the order of bounds.
+    pub fn difference_by_key<K, F>(self, history: Vec<Self>, key: F) -> Difference<Bound, M>
+    where
+        K: BoundOps + Copy,
+        F: Fn(&Bound) -> K,
+    {
+        let mut bounds = BTreeMap::new();
+        let mut project = |iv: Self| {
+            let (lower, upper) = (key(&iv.lower), key(&iv.upper));
+            bounds.entry(lower).or_insert(iv.lower);
+            bounds.entry(upper).or_insert(iv.upper);
+            TaggedInterval {
+                lower,
+                upper,
+                tags: iv.tags,
+                meta: iv.meta,
             }
+        };
+        let specified = project(self);
+        let history = history.into_iter().map(&mut project).collect();
+        specified
+            .difference(history)
+            .into_iter()
+            .map(|iv| Self {
+                lower: bounds[&iv.lower].clone(),
+                upper: bounds[&iv.upper].clone(),
+                tags: iv.tags,
+                meta: iv.meta,
+            })
+            .collect()
+    }
+
+    /// Like `difference`, but the history is queried from `source`.
+    pub fn difference_from<S>(self, source: &S) -> Difference<Bound, M>
+    where
+        S: CoverageSource<Bound, M> + ?Sized,
+    {
+        let history = source.covered(self.lower.clone(), self.upper.clone(), &self.tags);
+        self.difference(history)
+    }
+
+    /// Like `difference`, but whether a tag counts as covered is decided by
+    /// `coverage`.
+    pub fn difference_with_coverage<C>(
+        self,
+        history: Vec<Self>,
+        coverage: &C,
+    ) -> Difference<Bound, M>
+    where
+        C: Coverage<Bound, M>,
+    {
+        coverage::sweep(&self, &history, coverage).into()
+    }
+
+    /// Like `difference`, but computed from a `BTreeMap` of coverage deltas
+    /// rather than the sweep. Zero-length history entries are ignored.
+    pub fn difference_with_boundary_map(self, history: Vec<Self>) -> Difference<Bound, M> {
+        boundary_map::difference(&self, &history).into()
+    }
+
+    /// Like `difference`, but computed with per-tag bitsets over the cells of
+    /// the specified range. Faster than the sweep for integer bounds over a
+    /// small range with many short history entries. Ranges of more than
+    /// 2^24 cells are swept instead.
+    pub fn difference_with_bitset(self, history: Vec<Self>) -> Difference<Bound, M>
+    where
+        Bound: Into<i128> + TryFrom<i128>,
+    {
+        bitset::difference(&self, &history).into()
+    }
+
+    /// Like `difference`, but the window is split into up to `partitions`
+    /// parts that are swept concurrently.
+    pub fn difference_parallel(self, history: Vec<Self>, partitions: usize) -> Difference<Bound, M>
+    where
+        Bound: Send + Sync,
+        M: Send + Sync,
+    {
+        parallel::difference(&self, &history, partitions).into()
+    }
+
+    /// Like `difference`, but a tag counts as covered only where at least
+    /// `multiplicity` history entries carry it. Identical entries count
+    /// separately, as they usually come from separate sources.
+    pub fn difference_with_multiplicity(
+        self,
+        history: Vec<Self>,
+        multiplicity: usize,
+    ) -> Difference<Bound, M> {
+        if multiplicity == 0 {
+            return Difference::default();
+        }
+
+        self.difference_with_coverage(history, &coverage::AtLeast(multiplicity))
+    }
+
+    /// Like `difference`, but each history entry carries a confidence and a
+    /// tag counts as covered only where the aggregated confidence of the
+    /// entries carrying it exceeds `threshold`.
+    pub fn difference_with_confidence(
+        self,
+        history: Vec<(Self, f64)>,
+        threshold: f64,
+        aggregate: ConfidenceAggregate,
+    ) -> Difference<Bound, M> {
+        let (history, confidences): (Vec<Self>, Vec<f64>) = history.into_iter().unzip();
+
+        let weighted = coverage::Weighted {
+            confidences: &confidences,
+            threshold,
+            aggregate,
+        };
+        self.difference_with_coverage(history, &weighted)
+    }
+
+    /// Like `difference`, but history is an event log processed in order:
+    /// for each tag, the latest event spanning a point decides whether the
+    /// point is covered, so a `Revoke` withdraws earlier `Cover` claims.
+    pub fn difference_with_overrides(
+        self,
+        events: Vec<HistoryEvent<Bound, M>>,
+    ) -> Difference<Bound, M> {
+        let (history, revoked): (Vec<Self>, Vec<bool>) = events
+            .into_iter()
+            .map(|event| match event {
+                HistoryEvent::Cover(iv) => (iv, false),
+                HistoryEvent::Revoke(iv) => (iv, true),
+            })
+            .unzip();
+
+        let overrides = coverage::LastWriterWins { revoked: &revoked };
+        self.difference_with_coverage(history, &overrides)
+    }
+
+    /// Like `difference`, but each history entry may carry an expiry bound;
+    /// entries that have expired as of `now` no longer count as coverage.
+    pub fn difference_with_expiry(
+        self,
+        history: Vec<(Self, Option<Bound>)>,
+        now: Bound,
+    ) -> Difference<Bound, M> {
+        let history = history
+            .into_iter()
+            .filter(|(_, expiry)| expiry.as_ref().is_none_or(|expiry| now < *expiry))
+            .map(|(iv, _)| iv)
+            .collect();
+        self.difference(history)
+    }
+
+    /// Like `difference`, but each history entry carries the bound at which it
+    /// was fetched; entries fetched more than `max_age` before `now` are
+    /// ignored. Entries fetched at or after `now`, e.g. due to clock skew, are
+    /// fresh.
+    pub fn difference_with_max_age<D>(
+        self,
+        history: Vec<(Self, Bound)>,
+        now: Bound,
+        max_age: D,
+    ) -> Difference<Bound, M>
+    where
+        Bound: Sub<Output = D>,
+        D: Ord,
+    {
+        let history = history
+            .into_iter()
+            .filter(|(_, fetched_at)| {
+                *fetched_at >= now || now.clone() - fetched_at.clone() <= max_age
+            })
+            .map(|(iv, _)| iv)
+            .collect();
+        self.difference(history)
+    }
+
+    /// Like `difference`, but the meta of each output interval is computed by
+    /// `merge` from the specified meta and the metas of the history entries
+    /// spanning it.
+    pub fn difference_with_meta<F>(self, history: Vec<Self>, merge: F) -> Difference<Bound, M>
+    where
+        F: Fn(Option<&M>, &[&M]) -> Option<M>,
+    {
+        let merged = coverage::MergedMeta {
+            merge,
+            history: &history,
+        };
+        coverage::sweep(&self, &history, &merged).into()
+    }
+
+    /// Like `difference`, but the reported tags of each range are decided by
+    /// `strategy` instead of being the specified tags not covered there.
+    pub fn difference_by<S>(self, history: Vec<Self>, strategy: &S) -> Difference<Bound, M>
+    where
+        S: OutputTags<Bound, M>,
+    {
+        let by_output_tags = coverage::ByOutputTags {
+            strategy,
+            specified: &self,
+            history: &history,
+        };
+        coverage::sweep(&self, &history, &by_output_tags).into()
+    }
+
+    /// The dual of `difference`: returns the sub-ranges of `self` where some
+    /// specified tags are covered by `history`, tagged with those tags.
+    pub fn intersection(self, history: &[Self]) -> Vec<Self> {
+        let complement = coverage::Complement {
+            inner: &coverage::Union,
+            tags: &self.tags,
+        };
+        coverage::sweep(&self, history, &complement)
+    }
 
-            let mut next_tags = difference_with_dups(&current_tags, &upper_tags);
-            next_tags.append(&mut lower_tags);
+    /// Returns the union of `self` and `other`; see `union_all`.
+    pub fn union(self, other: Self) -> Vec<Self> {
+        Self::union_all(vec![self, other])
+    }
 
-            let continuous = in_specified_range
-                && HashSet::<String>::from_iter(next_tags.iter().cloned())
-                    .eq(&HashSet::from_iter(current_tags.iter().cloned()));
+    /// Returns the union of `intervals` as disjoint ranges ordered by lower
+    /// bound, each tagged with every tag covering it. Adjacent ranges with
+    /// the same tags are joined, and the meta is dropped.
+    pub fn union_all(intervals: Vec<Self>) -> Vec<Self> {
+        let lower = intervals.iter().map(|iv| &iv.lower).min();
+        let upper = intervals.iter().map(|iv| &iv.upper).max();
+        let (lower, upper) = match (lower, upper) {
+            (Some(lower), Some(upper)) => (lower.clone(), upper.clone()),
+            _ => return vec![],
+        };
+        let tags = intervals
+            .iter()
+            .flat_map(|iv| iv.tags.iter().cloned())
+            .collect();
+        let window = Self {
+            lower,
+            upper,
+            tags,
+            meta: None,
+        };
+        let history: Vec<Self> = intervals
+            .into_iter()
+            .map(|iv| Self { meta: None, ..iv })
+            .collect();
+        window.intersection(&history)
+    }
 
-            if in_specified_range && (!continuous || specified_range_will_be_over) {
-                let current_tag_set = current_tags.iter().cloned().collect();
-                let tags: HashSet<String> =
-                    self.tags.difference(&current_tag_set).cloned().collect();
-                if !tags.is_empty() {
-                    let tagged_bound = TaggedInterval::new(current_bound, bounds[i].bound, tags);
-                    result.push(tagged_bound);
+    /// Returns the parts of `history` that `self` does not ask for: the parts
+    /// of each entry outside the window with all of its tags, and the part
+    /// inside with the tags that are not specified. Ordered by lower bound.
+    pub fn excess(self, history: Vec<Self>) -> Vec<Self> {
+        let mut result = vec![];
+        for iv in history {
+            let inside = (
+                iv.lower.clone().max(self.lower.clone()),
+                iv.upper.clone().min(self.upper.clone()),
+            );
+            let parts = [
+                (
+                    iv.lower.clone(),
+                    iv.upper.clone().min(self.lower.clone()),
+                    iv.tags.clone(),
+                ),
+                (
+                    inside.0,
+                    inside.1,
+                    iv.tags.difference(&self.tags).cloned().collect(),
+                ),
+                (
+                    iv.lower.clone().max(self.upper.clone()),
+                    iv.upper.clone(),
+                    iv.tags.clone(),
+                ),
+            ];
+            for (lower, upper, tags) in parts {
+                if lower < upper && !tags.is_empty() {
+                    result.push(Self {
+                        lower,
+                        upper,
+                        tags,
+                        meta: iv.meta.clone(),
+                    });
                 }
             }
+        }
+        result.sort_by(|x, y| x.lower.cmp(&y.lower));
+        result
+    }
+
+    /// Like `difference`, but the `blackouts` ranges are treated as covered
+    /// for every specified tag and are never reported as missing.
+    pub fn difference_excluding(
+        self,
+        mut history: Vec<Self>,
+        blackouts: &[(Bound, Bound)],
+    ) -> Difference<Bound, M> {
+        history.extend(blackouts.iter().map(|(lower, upper)| Self {
+            lower: lower.clone(),
+            upper: upper.clone(),
+            tags: self.tags.clone(),
+            meta: None,
+        }));
+        self.difference(history)
+    }
 
-            if specified_range_will_be_over {
-                break;
+    /// Like `difference`, but ignores gaps of a tag shorter than its entry in
+    /// `tolerances`. Tags without an entry tolerate no gaps.
+    pub fn difference_with_tolerance<D>(
+        self,
+        history: Vec<Self>,
+        tolerances: &HashMap<String, D>,
+    ) -> Difference<Bound, M>
+    where
+        Bound: Sub<Output = D>,
+        D: Ord,
+    {
+        let missing = self.difference(history);
+        let gaps = flatten_per_tag(&missing);
+        let tolerated = |tag: &String, point: &Bound| {
+            tolerances.get(tag).is_some_and(|tolerance| {
+                let spans = &gaps[tag];
+                let k = spans.partition_point(|(l, _)| l <= point);
+                let (l, u) = &spans[k - 1];
+                u.clone() - l.clone() < *tolerance
+            })
+        };
+
+        let mut result: Vec<Self> = vec![];
+        for mut iv in missing {
+            let lower = iv.lower.clone();
+            iv.tags.retain(|t| !tolerated(t, &lower));
+            if iv.tags.is_empty() {
+                continue;
             }
-            if specified_lower_found {
-                in_specified_range = true;
+            match result.last_mut() {
+                Some(last) if last.upper == iv.lower && last.tags == iv.tags => {
+                    last.upper = iv.upper
+                }
+                _ => result.push(iv),
             }
-            if !continuous {
-                current_bound = bounds[i].bound;
+        }
+        result.into()
+    }
+
+    /// Like `difference`, but sweeps from the upper end downward, so the
+    /// output is ordered newest first.
+    pub fn difference_descending(self, history: Vec<Self>) -> Difference<Bound, M> {
+        let reverse = |iv: Self| TaggedInterval {
+            lower: Reverse(iv.upper),
+            upper: Reverse(iv.lower),
+            tags: iv.tags,
+            meta: iv.meta,
+        };
+        let specified = reverse(self);
+        let history = history.into_iter().map(reverse).collect();
+        specified
+            .difference(history)
+            .into_iter()
+            .map(|iv| Self {
+                lower: iv.upper.0,
+                upper: iv.lower.0,
+                tags: iv.tags,
+                meta: iv.meta,
+            })
+            .collect()
+    }
+
+    /// Like `difference`, but for several disjoint specified windows at once;
+    /// the output is confined to the windows and ordered by them.
+    pub fn difference_multi(specified: &[Self], history: &[Self]) -> Difference<Bound, M> {
+        let mut windows = specified.to_vec();
+        windows.sort_by(|x, y| x.lower.cmp(&y.lower));
+        windows
+            .into_iter()
+            .flat_map(|w| {
+                let history = clip_history(&w, history);
+                w.difference(history)
+            })
+            .collect()
+    }
+
+    /// Like `difference`, but for many goals, each a window with its own
+    /// tags, keyed by `K`. The history is sorted once and shared by all goals.
+    pub fn difference_goals<K>(
+        goals: &[(K, Self)],
+        history: &[Self],
+    ) -> HashMap<K, Difference<Bound, M>>
+    where
+        K: Clone + Eq + Hash,
+    {
+        let mut sorted: Vec<&Self> = history.iter().collect();
+        sorted.sort_by(|x, y| x.lower.cmp(&y.lower));
+
+        goals
+            .iter()
+            .map(|(key, goal)| {
+                let end = sorted.partition_point(|iv| iv.lower < goal.upper);
+                let history = sorted[..end]
+                    .iter()
+                    .filter(|iv| goal.lower < iv.upper && !iv.tags.is_disjoint(&goal.tags))
+                    .map(|iv| (*iv).clone())
+                    .collect();
+                (key.clone(), goal.clone().difference(history))
+            })
+            .collect()
+    }
+
+    /// Returns the keys of the goals whose window `self` overlaps with some of
+    /// their tags, i.e. the goals this history entry contributes to.
+    pub fn goals_served<K>(&self, goals: &[(K, Self)]) -> Vec<K>
+    where
+        K: Clone,
+    {
+        goals
+            .iter()
+            .filter(|(_, goal)| {
+                self.lower < goal.upper
+                    && goal.lower < self.upper
+                    && !self.tags.is_disjoint(&goal.tags)
+            })
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Returns the longest range over which each tag is missing, with gaps
+    /// split across output intervals joined together.
+    pub fn longest_gap_per_tag<D>(&self, history: &[Self]) -> HashMap<String, (Bound, Bound)>
+    where
+        Bound: Sub<Output = D>,
+        D: Ord,
+    {
+        let missing = self.clone().difference(history.to_vec());
+        flatten_per_tag(&missing)
+            .into_iter()
+            .filter_map(|(tag, spans)| {
+                let longest = spans.into_iter().min_by(|(l1, u1), (l2, u2)| {
+                    (u2.clone() - l2.clone())
+                        .cmp(&(u1.clone() - l1.clone()))
+                        .then(l1.cmp(l2))
+                })?;
+                Some((tag, longest))
+            })
+            .collect()
+    }
+
+    /// Returns the longest range over which some tag is missing, tagged with
+    /// every tag missing throughout it.
+    pub fn longest_gap<D>(&self, history: &[Self]) -> Option<Self>
+    where
+        Bound: Sub<Output = D>,
+        D: Ord,
+    {
+        let missing = flatten_per_tag(&self.clone().difference(history.to_vec()));
+        let (lower, upper) = missing
+            .values()
+            .flatten()
+            .min_by(|(l1, u1), (l2, u2)| {
+                (u2.clone() - l2.clone())
+                    .cmp(&(u1.clone() - l1.clone()))
+                    .then(l1.cmp(l2))
+            })
+            .cloned()?;
+        let tags = missing
+            .into_iter()
+            .filter(|(_, spans)| spans.iter().any(|(l, u)| *l <= lower && upper <= *u))
+            .map(|(tag, _)| tag)
+            .collect();
+        Some(Self {
+            lower,
+            upper,
+            tags,
+            meta: self.meta.clone(),
+        })
+    }
+}
+
+/// Returns the coverage of each tag in `history` as sorted, disjoint and
+/// coalesced `(lower, upper)` pairs.
+pub fn flatten_per_tag<Bound, M>(
+    history: &[TaggedInterval<Bound, M>],
+) -> HashMap<String, Vec<(Bound, Bound)>>
+where
+    Bound: BoundOps,
+{
+    let mut spans: HashMap<String, Vec<(Bound, Bound)>> = HashMap::new();
+    for iv in history.iter().filter(|iv| iv.lower < iv.upper) {
+        for t in iv.tags.iter() {
+            spans
+                .entry(t.clone())
+                .or_default()
+                .push((iv.lower.clone(), iv.upper.clone()));
+        }
+    }
+
+    for tag_spans in spans.values_mut() {
+        tag_spans.sort();
+        let mut coalesced: Vec<(Bound, Bound)> = vec![];
+        for (lower, upper) in tag_spans.drain(..) {
+            match coalesced.last_mut() {
+                Some(last) if lower <= last.1 => last.1 = last.1.clone().max(upper),
+                _ => coalesced.push((lower, upper)),
             }
+        }
+        *tag_spans = coalesced;
+    }
+    spans
+}
 
-            i = j;
-            current_tags = next_tags;
+/// Like `flatten_per_tag`, but keyed by `key` parsed from each tag, e.g. a
+/// shard or customer ID. Tags `key` rejects are skipped, and tags parsing to
+/// the same key are merged.
+pub fn by_key<Bound, M, K, F>(
+    intervals: &[TaggedInterval<Bound, M>],
+    key: F,
+) -> HashMap<K, Vec<(Bound, Bound)>>
+where
+    Bound: BoundOps,
+    K: Eq + Hash,
+    F: Fn(&str) -> Option<K>,
+{
+    let mut grouped: HashMap<K, Vec<(Bound, Bound)>> = HashMap::new();
+    for (tag, spans) in flatten_per_tag(intervals) {
+        if let Some(k) = key(&tag) {
+            grouped.entry(k).or_default().extend(spans);
         }
+    }
+    grouped
+        .into_iter()
+        .map(|(k, spans)| (k, timeline::Timeline::from_spans(&spans).spans()))
+        .collect()
+}
 
-        result
+/// Returns for each of `points` whether all of `tags` are covered by
+/// `history` there, in a single pass over the sorted points and bounds.
+pub fn covered_points<Bound, M>(
+    history: &[TaggedInterval<Bound, M>],
+    points: &[Bound],
+    tags: &HashSet<String>,
+) -> Vec<bool>
+where
+    Bound: BoundOps,
+{
+    let mut events: Vec<(Bound, bool, &HashSet<String>)> = history
+        .iter()
+        .filter(|iv| iv.lower < iv.upper)
+        .flat_map(|iv| {
+            vec![
+                (iv.lower.clone(), true, &iv.tags),
+                (iv.upper.clone(), false, &iv.tags),
+            ]
+        })
+        .collect();
+    events.sort_by(|x, y| x.0.cmp(&y.0));
+
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by(|x, y| points[*x].cmp(&points[*y]));
+
+    let mut result = vec![false; points.len()];
+    let mut counts: HashMap<&String, usize> = HashMap::new();
+    let mut events = events.into_iter().peekable();
+    for i in order {
+        while let Some((_, entering, event_tags)) = events.next_if(|e| e.0 <= points[i]) {
+            for t in event_tags.iter().filter(|t| tags.contains(*t)) {
+                let n = counts.entry(t).or_insert(0);
+                if entering {
+                    *n += 1;
+                } else {
+                    *n -= 1;
+                }
+            }
+        }
+        result[i] = tags.iter().all(|t| counts.get(t).is_some_and(|n| *n > 0));
     }
+    result
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use chrono::prelude::*;
+/// Returns the coalesced covered range of `tag` containing `point`, or else
+/// the closest one, preferring the earlier range on ties.
+pub fn nearest_covered<Bound, M, D>(
+    history: &[TaggedInterval<Bound, M>],
+    point: Bound,
+    tag: &str,
+) -> Option<(Bound, Bound)>
+where
+    Bound: BoundOps + Sub<Output = D>,
+    D: Ord,
+{
+    let spans = flatten_per_tag(history).remove(tag)?;
+    spans.into_iter().min_by(|x, y| {
+        let distance = |(lower, upper): &(Bound, Bound)| {
+            if point < *lower {
+                Some(lower.clone() - point.clone())
+            } else if *upper <= point {
+                Some(point.clone() - upper.clone())
+            } else {
+                None
+            }
+        };
+        distance(x).cmp(&distance(y)).then(x.cmp(y))
+    })
+}
 
-    type Time = DateTime<Utc>;
+/// Removes the entries shorter than `min_length` from `history` and returns
+/// them so that they can be reported.
+pub fn drop_short_entries<Bound, M, D>(
+    history: &mut Vec<TaggedInterval<Bound, M>>,
+    min_length: D,
+) -> Vec<TaggedInterval<Bound, M>>
+where
+    Bound: BoundOps + Sub<Output = D>,
+    D: Ord,
+{
+    let (kept, dropped) = history
+        .drain(..)
+        .partition(|iv| iv.upper.clone() - iv.lower.clone() >= min_length);
+    *history = kept;
+    dropped
+}
 
-    fn tiv<'a>(lower: Time, upper: Time, tags: HashSet<String>) -> TaggedInterval<Time> {
-        TaggedInterval::new(lower, upper, tags)
+/// Returns the first zero-length entry of `history` as an error if `policy`
+/// rejects them.
+pub fn validate_zero_length<Bound, M>(
+    history: &[TaggedInterval<Bound, M>],
+    policy: ZeroLength,
+) -> Result<(), ZeroLengthError<Bound>>
+where
+    Bound: BoundOps,
+{
+    if policy != ZeroLength::Error {
+        return Ok(());
     }
+    match history.iter().position(|iv| iv.lower == iv.upper) {
+        Some(index) => Err(ZeroLengthError {
+            index,
+            bound: history[index].lower.clone(),
+        }),
+        None => Ok(()),
+    }
+}
 
-    fn time(s: &str) -> Time {
-        s.parse::<Time>().unwrap()
+/// Returns the entries of `history` overlapping `specified`, trimmed to its
+/// bounds. Clipping does not change the result of `difference` and shrinks
+/// the history it has to sort.
+pub fn clip_history<Bound, M>(
+    specified: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+) -> Vec<TaggedInterval<Bound, M>>
+where
+    Bound: BoundOps,
+    M: Clone,
+{
+    history
+        .iter()
+        .filter(|iv| iv.lower < specified.upper && specified.lower < iv.upper)
+        .map(|iv| TaggedInterval {
+            lower: iv.lower.clone().max(specified.lower.clone()),
+            upper: iv.upper.clone().min(specified.upper.clone()),
+            tags: iv.tags.clone(),
+            meta: iv.meta.clone(),
+        })
+        .collect()
+}
+
+/// Snaps the entries of `history` to multiples of `resolution`, dropping the
+/// ones that vanish when rounding inward.
+pub fn downsample<Bound, M, D>(
+    history: &[TaggedInterval<Bound, M>],
+    resolution: D,
+    rounding: Rounding,
+) -> Vec<TaggedInterval<Bound, M>>
+where
+    Bound: BoundOps + Snap<D>,
+    M: Clone,
+    D: Copy,
+{
+    history
+        .iter()
+        .map(|iv| {
+            let (lower, upper) = match rounding {
+                Rounding::Inward => (iv.lower.snap_up(resolution), iv.upper.snap_down(resolution)),
+                Rounding::Outward => (iv.lower.snap_down(resolution), iv.upper.snap_up(resolution)),
+            };
+            TaggedInterval {
+                lower,
+                upper,
+                tags: iv.tags.clone(),
+                meta: iv.meta.clone(),
+            }
+        })
+        .filter(|iv| iv.lower < iv.upper)
+        .collect()
+}
+
+/// Turns tagged point observations into coverage intervals, joining
+/// consecutive points of a tag at most `max_gap` apart. Each interval runs
+/// from the first to the last point of its run, so an isolated point yields
+/// a zero-length interval: it records the observation but, like any
+/// zero-length entry, covers nothing in `difference`. Ordered by lower bound
+/// and then by tag.
+pub fn intervals_from_points<Bound, D, I>(points: I, max_gap: D) -> Vec<TaggedInterval<Bound>>
+where
+    Bound: BoundOps + Sub<Output = D>,
+    D: Ord,
+    I: IntoIterator<Item = (Bound, String)>,
+{
+    let mut per_tag: HashMap<String, Vec<Bound>> = HashMap::new();
+    for (point, tag) in points {
+        per_tag.entry(tag).or_default().push(point);
     }
 
-    fn tags(strs: &[&str]) -> HashSet<String> {
-        strs.iter().cloned().map(|s| s.to_string()).collect()
+    let mut result = vec![];
+    for (tag, mut points) in per_tag {
+        points.sort();
+        let mut runs: Vec<(Bound, Bound)> = vec![];
+        for point in points {
+            match runs.last_mut() {
+                Some(last) if point.clone() - last.1.clone() <= max_gap => last.1 = point,
+                _ => runs.push((point.clone(), point)),
+            }
+        }
+        let tags: HashSet<String> = [tag].iter().cloned().collect();
+        result.extend(
+            runs.into_iter()
+                .map(|(l, u)| TaggedInterval::new(l, u, tags.clone())),
+        );
     }
+    result.sort_by(|x, y| (&x.lower, x.tags.iter().next()).cmp(&(&y.lower, y.tags.iter().next())));
+    result
+}
 
-    #[test]
-    fn difference_works() {
-        let cases = vec![
-            (
-                "empty (zero length)",
-                // specified
-                tiv(
-                    time("2077-07-07T09:00:00Z"),
-                    time("2077-07-07T09:00:00Z"),
-                    tags(&["freedom", "liberty"]),
-                ),
-                // history
-                vec![],
-                // expected
-                vec![],
-            ),
-            (
+/// Like `intervals_from_points`, but drops sessions shorter than
+/// `min_duration`, e.g. to turn log timestamps into coverage only where a
+/// service kept logging for a while. With a zero `min_duration`, isolated
+/// points are kept as zero-length sessions.
+pub fn sessions_from_points<Bound, D, I>(
+    points: I,
+    max_gap: D,
+    min_duration: D,
+) -> Vec<TaggedInterval<Bound>>
+where
+    Bound: BoundOps + Sub<Output = D>,
+    D: Ord,
+    I: IntoIterator<Item = (Bound, String)>,
+{
+    intervals_from_points(points, max_gap)
+        .into_iter()
+        .filter(|iv| iv.upper.clone() - iv.lower.clone() >= min_duration)
+        .collect()
+}
+
+/// A small deterministic generator for synthetic histories.
+#[cfg(any(test, feature = "bench"))]
+pub(crate) struct Lcg(pub(crate) u64);
+
+#[cfg(any(test, feature = "bench"))]
+impl Lcg {
+    /// Returns a number below `n`, or zero if `n` is zero.
+    pub(crate) fn next(&mut self, n: u64) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) % n.max(1)
+    }
+}
+
+/// Returns a random window asking for three of four tags and a history of
+/// fewer than `entries` random entries, some of zero length, all within
+/// `-2 * scale..3 * scale`.
+#[cfg(test)]
+pub(crate) fn random_case(
+    rng: &mut Lcg,
+    scale: u64,
+    entries: u64,
+) -> (TaggedInterval<i64>, Vec<TaggedInterval<i64>>) {
+    let all_tags = ["freedom", "liberty", "fairness", "democracy"];
+    let offset = scale as i64;
+    let lower = rng.next(2 * scale) as i64 - offset;
+    let specified = tiv(lower, lower + rng.next(2 * scale) as i64, &all_tags[..3]);
+    let history = (0..rng.next(entries))
+        .map(|_| {
+            let lower = rng.next(4 * scale) as i64 - 2 * offset;
+            let tags: Vec<&str> = all_tags
+                .iter()
+                .filter(|_| rng.next(2) == 0)
+                .cloned()
+                .collect();
+            tiv(lower, lower + rng.next(scale) as i64, &tags)
+        })
+        .collect();
+    (specified, history)
+}
+
+/// Builds an interval from bounds and tag names, for the tests of every
+/// module.
+#[cfg(test)]
+pub(crate) fn tiv<Bound>(lower: Bound, upper: Bound, tags: &[&str]) -> TaggedInterval<Bound>
+where
+    Bound: BoundOps,
+{
+    TaggedInterval::new(lower, upper, crate::tags(tags))
+}
+
+#[cfg(test)]
+pub(crate) fn tags(tags: &[&str]) -> HashSet<String> {
+    tags.iter().map(|t| t.to_string()).collect()
+}
+
+#[cfg(test)]
+pub(crate) fn time(s: &str) -> chrono::DateTime<chrono::Utc> {
+    s.parse().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::prelude::*;
+
+    type Time = DateTime<Utc>;
+
+    #[test]
+    fn difference_works() {
+        let cases = vec![
+            (
+                "empty (zero length)",
+                // specified
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T09:00:00Z"),
+                    &["freedom", "liberty"],
+                ),
+                // history
+                vec![],
+                // expected
+                vec![],
+            ),
+            (
                 "empty (no tags)",
                 // specified
                 tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&[]),
+                    &[],
                 ),
                 // history
                 vec![tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&[]),
+                    &[],
                 )],
                 // expected
                 vec![],
@@ -258,13 +1342,13 @@ mod tests {
                 tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["freedom", "liberty"]),
+                    &["freedom", "liberty"],
                 ),
                 // history
                 vec![tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["freedom", "liberty"]),
+                    &["freedom", "liberty"],
                 )],
                 // expected
                 vec![],
@@ -275,13 +1359,13 @@ mod tests {
                 tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["freedom", "liberty"]),
+                    &["freedom", "liberty"],
                 ),
                 // history
                 vec![tiv(
                     time("2077-07-07T08:00:00Z"),
                     time("2077-07-07T18:00:00Z"),
-                    tags(&["freedom", "liberty", "fairness"]),
+                    &["freedom", "liberty", "fairness"],
                 )],
                 // expected
                 vec![],
@@ -292,13 +1376,13 @@ mod tests {
                 tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["freedom"]),
+                    &["freedom"],
                 ),
                 // history
                 vec![tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T18:00:00Z"),
-                    tags(&["freedom"]),
+                    &["freedom"],
                 )],
                 // expected
                 vec![],
@@ -309,13 +1393,13 @@ mod tests {
                 tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["freedom"]),
+                    &["freedom"],
                 ),
                 // history
                 vec![tiv(
                     time("2077-07-07T08:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["freedom"]),
+                    &["freedom"],
                 )],
                 // expected
                 vec![],
@@ -326,19 +1410,19 @@ mod tests {
                 tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["freedom", "liberty"]),
+                    &["freedom", "liberty"],
                 ),
                 // history
                 vec![tiv(
                     time("2077-07-08T09:00:00Z"),
                     time("2077-07-08T17:00:00Z"),
-                    tags(&["freedom", "liberty"]),
+                    &["freedom", "liberty"],
                 )],
                 // expected
                 vec![tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["freedom", "liberty"]),
+                    &["freedom", "liberty"],
                 )],
             ),
             (
@@ -347,19 +1431,19 @@ mod tests {
                 tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["freedom", "liberty"]),
+                    &["freedom", "liberty"],
                 ),
                 // history
                 vec![tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["fairness"]),
+                    &["fairness"],
                 )],
                 // expected
                 vec![tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["freedom", "liberty"]),
+                    &["freedom", "liberty"],
                 )],
             ),
             (
@@ -368,19 +1452,19 @@ mod tests {
                 tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["freedom", "liberty", "fairness"]),
+                    &["freedom", "liberty", "fairness"],
                 ),
                 // history
                 vec![tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["freedom", "liberty"]),
+                    &["freedom", "liberty"],
                 )],
                 // expected
                 vec![tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["fairness"]),
+                    &["fairness"],
                 )],
             ),
             (
@@ -389,19 +1473,19 @@ mod tests {
                 tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["freedom", "liberty", "fairness", "democracy"]),
+                    &["freedom", "liberty", "fairness", "democracy"],
                 ),
                 // history
                 vec![
                     tiv(
                         time("2077-07-07T08:00:00Z"),
                         time("2077-07-07T12:00:00Z"),
-                        tags(&["freedom", "liberty"]),
+                        &["freedom", "liberty"],
                     ),
                     tiv(
                         time("2077-07-07T15:00:00Z"),
                         time("2077-07-07T18:00:00Z"),
-                        tags(&["liberty", "fairness"]),
+                        &["liberty", "fairness"],
                     ),
                 ],
                 // expected
@@ -409,17 +1493,17 @@ mod tests {
                     tiv(
                         time("2077-07-07T09:00:00Z"),
                         time("2077-07-07T12:00:00Z"),
-                        tags(&["fairness", "democracy"]),
+                        &["fairness", "democracy"],
                     ),
                     tiv(
                         time("2077-07-07T12:00:00Z"),
                         time("2077-07-07T15:00:00Z"),
-                        tags(&["freedom", "liberty", "fairness", "democracy"]),
+                        &["freedom", "liberty", "fairness", "democracy"],
                     ),
                     tiv(
                         time("2077-07-07T15:00:00Z"),
                         time("2077-07-07T17:00:00Z"),
-                        tags(&["freedom", "democracy"]),
+                        &["freedom", "democracy"],
                     ),
                 ],
             ),
@@ -429,26 +1513,26 @@ mod tests {
                 tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["freedom", "liberty", "fairness", "democracy"]),
+                    &["freedom", "liberty", "fairness", "democracy"],
                 ),
                 // history
                 vec![
                     tiv(
                         time("2077-07-07T09:00:00Z"),
                         time("2077-07-07T13:00:00Z"),
-                        tags(&["freedom", "liberty"]),
+                        &["freedom", "liberty"],
                     ),
                     tiv(
                         time("2077-07-07T13:00:00Z"),
                         time("2077-07-07T17:00:00Z"),
-                        tags(&["freedom", "liberty"]),
+                        &["freedom", "liberty"],
                     ),
                 ],
                 // expected
                 vec![tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["fairness", "democracy"]),
+                    &["fairness", "democracy"],
                 )],
             ),
             (
@@ -457,34 +1541,34 @@ mod tests {
                 tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["freedom", "liberty"]),
+                    &["freedom", "liberty"],
                 ),
                 // history
                 vec![
                     tiv(
                         time("2077-07-07T03:00:00Z"),
                         time("2077-07-07T11:00:00Z"),
-                        tags(&["freedom"]),
+                        &["freedom"],
                     ),
                     tiv(
                         time("2077-07-07T10:00:00Z"),
                         time("2077-07-07T13:00:00Z"),
-                        tags(&["liberty"]),
+                        &["liberty"],
                     ),
                     tiv(
                         time("2077-07-07T12:00:00Z"),
                         time("2077-07-07T15:00:00Z"),
-                        tags(&["freedom"]),
+                        &["freedom"],
                     ),
                     tiv(
                         time("2077-07-07T14:00:00Z"),
                         time("2077-07-07T17:00:00Z"),
-                        tags(&["liberty"]),
+                        &["liberty"],
                     ),
                     tiv(
                         time("2077-07-07T16:00:00Z"),
                         time("2077-07-07T23:59:00Z"),
-                        tags(&["freedom"]),
+                        &["freedom"],
                     ),
                 ],
                 // expected
@@ -492,22 +1576,22 @@ mod tests {
                     tiv(
                         time("2077-07-07T09:00:00Z"),
                         time("2077-07-07T10:00:00Z"),
-                        tags(&["liberty"]),
+                        &["liberty"],
                     ),
                     tiv(
                         time("2077-07-07T11:00:00Z"),
                         time("2077-07-07T12:00:00Z"),
-                        tags(&["freedom"]),
+                        &["freedom"],
                     ),
                     tiv(
                         time("2077-07-07T13:00:00Z"),
                         time("2077-07-07T14:00:00Z"),
-                        tags(&["liberty"]),
+                        &["liberty"],
                     ),
                     tiv(
                         time("2077-07-07T15:00:00Z"),
                         time("2077-07-07T16:00:00Z"),
-                        tags(&["freedom"]),
+                        &["freedom"],
                     ),
                 ],
             ),
@@ -517,39 +1601,39 @@ mod tests {
                 tiv(
                     time("2077-07-07T09:00:00Z"),
                     time("2077-07-07T17:00:00Z"),
-                    tags(&["freedom", "liberty", "fairness", "democracy"]),
+                    &["freedom", "liberty", "fairness", "democracy"],
                 ),
                 // history
                 vec![
                     tiv(
                         time("2077-07-07T09:00:00Z"),
                         time("2077-07-07T13:00:00Z"),
-                        tags(&["freedom", "liberty"]),
+                        &["freedom", "liberty"],
                     ),
                     tiv(
                         time("2077-07-07T09:00:00Z"),
                         time("2077-07-07T13:00:00Z"),
-                        tags(&["liberty", "fairness"]),
+                        &["liberty", "fairness"],
                     ),
                     tiv(
                         time("2077-07-07T13:00:00Z"),
                         time("2077-07-07T15:00:00Z"),
-                        tags(&["freedom", "fairness"]),
+                        &["freedom", "fairness"],
                     ),
                     tiv(
                         time("2077-07-07T13:00:00Z"),
                         time("2077-07-07T15:00:00Z"),
-                        tags(&["fairness", "democracy"]),
+                        &["fairness", "democracy"],
                     ),
                     tiv(
                         time("2077-07-07T15:00:00Z"),
                         time("2077-07-07T17:00:00Z"),
-                        tags(&["freedom"]),
+                        &["freedom"],
                     ),
                     tiv(
                         time("2077-07-07T15:00:00Z"),
                         time("2077-07-07T17:00:00Z"),
-                        tags(&["democracy"]),
+                        &["democracy"],
                     ),
                 ],
                 // expected
@@ -557,17 +1641,17 @@ mod tests {
                     tiv(
                         time("2077-07-07T09:00:00Z"),
                         time("2077-07-07T13:00:00Z"),
-                        tags(&["democracy"]),
+                        &["democracy"],
                     ),
                     tiv(
                         time("2077-07-07T13:00:00Z"),
                         time("2077-07-07T15:00:00Z"),
-                        tags(&["liberty"]),
+                        &["liberty"],
                     ),
                     tiv(
                         time("2077-07-07T15:00:00Z"),
                         time("2077-07-07T17:00:00Z"),
-                        tags(&["liberty", "fairness"]),
+                        &["liberty", "fairness"],
                     ),
                 ],
             ),
@@ -577,4 +1661,1663 @@ mod tests {
             assert_eq!(specified.difference(history), expected, "{}", name)
         }
     }
+
+    #[test]
+    fn difference_with_multiplicity_works() {
+        let cases = vec![
+            (
+                "zero multiplicity",
+                // specified
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["freedom", "liberty"],
+                ),
+                // history
+                vec![],
+                // multiplicity
+                0,
+                // expected
+                vec![],
+            ),
+            (
+                "single source is not enough",
+                // specified
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["freedom", "liberty"],
+                ),
+                // history
+                vec![tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["freedom", "liberty"],
+                )],
+                // multiplicity
+                2,
+                // expected
+                vec![tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["freedom", "liberty"],
+                )],
+            ),
+            (
+                "partially doubly covered",
+                // specified
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["freedom", "liberty"],
+                ),
+                // history
+                vec![
+                    tiv(
+                        time("2077-07-07T08:00:00Z"),
+                        time("2077-07-07T13:00:00Z"),
+                        &["freedom", "liberty"],
+                    ),
+                    tiv(
+                        time("2077-07-07T11:00:00Z"),
+                        time("2077-07-07T18:00:00Z"),
+                        &["freedom"],
+                    ),
+                ],
+                // multiplicity
+                2,
+                // expected
+                vec![
+                    tiv(
+                        time("2077-07-07T09:00:00Z"),
+                        time("2077-07-07T11:00:00Z"),
+                        &["freedom", "liberty"],
+                    ),
+                    tiv(
+                        time("2077-07-07T11:00:00Z"),
+                        time("2077-07-07T13:00:00Z"),
+                        &["liberty"],
+                    ),
+                    tiv(
+                        time("2077-07-07T13:00:00Z"),
+                        time("2077-07-07T17:00:00Z"),
+                        &["freedom", "liberty"],
+                    ),
+                ],
+            ),
+            (
+                "identical entries count separately",
+                // specified
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["freedom"],
+                ),
+                // history
+                vec![
+                    tiv(
+                        time("2077-07-07T09:00:00Z"),
+                        time("2077-07-07T17:00:00Z"),
+                        &["freedom"],
+                    ),
+                    tiv(
+                        time("2077-07-07T09:00:00Z"),
+                        time("2077-07-07T13:00:00Z"),
+                        &["freedom"],
+                    ),
+                    tiv(
+                        time("2077-07-07T09:00:00Z"),
+                        time("2077-07-07T17:00:00Z"),
+                        &["freedom"],
+                    ),
+                ],
+                // multiplicity
+                3,
+                // expected
+                vec![tiv(
+                    time("2077-07-07T13:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["freedom"],
+                )],
+            ),
+        ];
+
+        for (name, specified, history, multiplicity, expected) in cases {
+            assert_eq!(
+                specified.difference_with_multiplicity(history, multiplicity),
+                expected,
+                "{}",
+                name
+            )
+        }
+    }
+
+    #[test]
+    fn difference_with_confidence_works() {
+        let cases = vec![
+            (
+                "max below threshold",
+                // specified
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["freedom"],
+                ),
+                // history
+                vec![
+                    (
+                        tiv(
+                            time("2077-07-07T09:00:00Z"),
+                            time("2077-07-07T17:00:00Z"),
+                            &["freedom"],
+                        ),
+                        0.5,
+                    ),
+                    (
+                        tiv(
+                            time("2077-07-07T09:00:00Z"),
+                            time("2077-07-07T13:00:00Z"),
+                            &["freedom"],
+                        ),
+                        0.5,
+                    ),
+                ],
+                // aggregate
+                ConfidenceAggregate::Max,
+                // expected
+                vec![tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["freedom"],
+                )],
+            ),
+            (
+                "max at threshold",
+                // specified
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["freedom"],
+                ),
+                // history
+                vec![(
+                    tiv(
+                        time("2077-07-07T09:00:00Z"),
+                        time("2077-07-07T17:00:00Z"),
+                        &["freedom"],
+                    ),
+                    0.8,
+                )],
+                // aggregate
+                ConfidenceAggregate::Max,
+                // expected
+                vec![tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["freedom"],
+                )],
+            ),
+            (
+                "sum exceeds threshold",
+                // specified
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["freedom", "liberty"],
+                ),
+                // history
+                vec![
+                    (
+                        tiv(
+                            time("2077-07-07T09:00:00Z"),
+                            time("2077-07-07T17:00:00Z"),
+                            &["freedom", "liberty"],
+                        ),
+                        0.5,
+                    ),
+                    (
+                        tiv(
+                            time("2077-07-07T09:00:00Z"),
+                            time("2077-07-07T13:00:00Z"),
+                            &["freedom"],
+                        ),
+                        0.5,
+                    ),
+                ],
+                // aggregate
+                ConfidenceAggregate::Sum,
+                // expected
+                vec![
+                    tiv(
+                        time("2077-07-07T09:00:00Z"),
+                        time("2077-07-07T13:00:00Z"),
+                        &["liberty"],
+                    ),
+                    tiv(
+                        time("2077-07-07T13:00:00Z"),
+                        time("2077-07-07T17:00:00Z"),
+                        &["freedom", "liberty"],
+                    ),
+                ],
+            ),
+        ];
+
+        for (name, specified, history, aggregate, expected) in cases {
+            assert_eq!(
+                specified.difference_with_confidence(history, 0.8, aggregate),
+                expected,
+                "{}",
+                name
+            )
+        }
+    }
+
+    #[test]
+    fn difference_with_overrides_works() {
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            &["freedom", "liberty"],
+        );
+        let events = vec![
+            HistoryEvent::Cover(tiv(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T17:00:00Z"),
+                &["freedom", "liberty"],
+            )),
+            HistoryEvent::Revoke(tiv(
+                time("2077-07-07T11:00:00Z"),
+                time("2077-07-07T15:00:00Z"),
+                &["freedom"],
+            )),
+            HistoryEvent::Cover(tiv(
+                time("2077-07-07T13:00:00Z"),
+                time("2077-07-07T18:00:00Z"),
+                &["freedom"],
+            )),
+        ];
+        let expected = vec![tiv(
+            time("2077-07-07T11:00:00Z"),
+            time("2077-07-07T13:00:00Z"),
+            &["freedom"],
+        )];
+
+        assert_eq!(specified.difference_with_overrides(events), expected);
+    }
+
+    #[test]
+    fn difference_with_expiry_works() {
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            &["freedom", "liberty"],
+        );
+        let history = vec![
+            (
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["freedom"],
+                ),
+                None,
+            ),
+            (
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T13:00:00Z"),
+                    &["liberty"],
+                ),
+                Some(time("2077-08-06T00:00:00Z")),
+            ),
+            (
+                tiv(
+                    time("2077-07-07T13:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["liberty"],
+                ),
+                Some(time("2077-08-07T00:00:00Z")),
+            ),
+        ];
+        let expected = vec![tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T13:00:00Z"),
+            &["liberty"],
+        )];
+
+        assert_eq!(
+            specified.difference_with_expiry(history, time("2077-08-06T00:00:00Z")),
+            expected
+        );
+    }
+
+    #[test]
+    fn difference_with_max_age_works() {
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            &["freedom"],
+        );
+        let history = vec![
+            (
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T13:00:00Z"),
+                    &["freedom"],
+                ),
+                time("2077-07-01T00:00:00Z"),
+            ),
+            (
+                tiv(
+                    time("2077-07-07T13:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["freedom"],
+                ),
+                time("2077-07-08T00:00:00Z"),
+            ),
+        ];
+        let expected = vec![tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T13:00:00Z"),
+            &["freedom"],
+        )];
+
+        assert_eq!(
+            specified.difference_with_max_age(
+                history,
+                time("2077-08-06T00:00:00Z"),
+                chrono::Duration::days(30)
+            ),
+            expected
+        );
+
+        // Fetched after `now` with unsigned bounds.
+        let specified = TaggedInterval::new(0u64, 100, tags(&["freedom"]));
+        let history = vec![
+            (TaggedInterval::new(0, 50, tags(&["freedom"])), 1000),
+            (TaggedInterval::new(50, 100, tags(&["freedom"])), 10),
+        ];
+        assert_eq!(
+            specified.difference_with_max_age(history, 500, 100),
+            vec![TaggedInterval::new(50, 100, tags(&["freedom"]))]
+        );
+    }
+
+    #[test]
+    fn difference_with_meta_works() {
+        let specified = TaggedInterval::with_meta(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            tags(&["freedom", "liberty"]),
+            "goal".to_string(),
+        );
+        let history = vec![
+            TaggedInterval::with_meta(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T13:00:00Z"),
+                tags(&["freedom"]),
+                "a".to_string(),
+            ),
+            TaggedInterval::with_meta(
+                time("2077-07-07T11:00:00Z"),
+                time("2077-07-07T17:00:00Z"),
+                tags(&["liberty"]),
+                "b".to_string(),
+            ),
+        ];
+        let expected = vec![
+            TaggedInterval::with_meta(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T11:00:00Z"),
+                tags(&["liberty"]),
+                "goal+a".to_string(),
+            ),
+            TaggedInterval::with_meta(
+                time("2077-07-07T13:00:00Z"),
+                time("2077-07-07T17:00:00Z"),
+                tags(&["freedom"]),
+                "goal+b".to_string(),
+            ),
+        ];
+
+        let merge = |specified: Option<&String>, covering: &[&String]| {
+            let mut metas: Vec<&str> = specified.into_iter().map(|m| m.as_str()).collect();
+            metas.extend(covering.iter().map(|m| m.as_str()));
+            Some(metas.join("+"))
+        };
+        assert_eq!(specified.difference_with_meta(history, merge), expected);
+    }
+
+    #[test]
+    fn difference_by_works() {
+        struct Majority {
+            replicas: usize,
+        }
+
+        impl OutputTags<Time> for Majority {
+            fn output_tags(
+                &self,
+                specified: &TaggedInterval<Time>,
+                covering: &[&TaggedInterval<Time>],
+            ) -> HashSet<String> {
+                specified
+                    .tags
+                    .iter()
+                    .filter(|t| {
+                        covering.iter().filter(|iv| iv.tags.contains(*t)).count() * 2
+                            < self.replicas
+                    })
+                    .cloned()
+                    .collect()
+            }
+        }
+
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            &["freedom", "liberty"],
+        );
+        let history = vec![
+            tiv(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T17:00:00Z"),
+                &["freedom", "liberty"],
+            ),
+            tiv(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T13:00:00Z"),
+                &["freedom"],
+            ),
+        ];
+
+        assert_eq!(
+            specified.clone().difference_by(history.clone(), &Uncovered),
+            specified.clone().difference(history.clone())
+        );
+        assert_eq!(
+            specified.difference_by(history, &Majority { replicas: 4 }),
+            vec![
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T13:00:00Z"),
+                    &["liberty"],
+                ),
+                tiv(
+                    time("2077-07-07T13:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["freedom", "liberty"],
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn difference_from_works() {
+        struct BeforeHorizon(Time);
+
+        impl CoverageSource<Time> for BeforeHorizon {
+            fn covered(
+                &self,
+                lower: Time,
+                upper: Time,
+                tags: &HashSet<String>,
+            ) -> Vec<TaggedInterval<Time>> {
+                if lower < self.0 {
+                    vec![TaggedInterval::new(lower, upper.min(self.0), tags.clone())]
+                } else {
+                    vec![]
+                }
+            }
+        }
+
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            &["freedom", "liberty"],
+        );
+        let history = vec![
+            tiv(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T13:00:00Z"),
+                &["freedom"],
+            ),
+            tiv(
+                time("2077-07-08T09:00:00Z"),
+                time("2077-07-08T13:00:00Z"),
+                &["liberty"],
+            ),
+        ];
+
+        assert_eq!(
+            specified.clone().difference_from(&history),
+            specified.clone().difference(history)
+        );
+        assert_eq!(
+            specified.difference_from(&BeforeHorizon(time("2077-07-07T15:00:00Z"))),
+            vec![tiv(
+                time("2077-07-07T15:00:00Z"),
+                time("2077-07-07T17:00:00Z"),
+                &["freedom", "liberty"],
+            )]
+        );
+    }
+
+    #[test]
+    fn flatten_per_tag_works() {
+        let history = vec![
+            tiv(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T13:00:00Z"),
+                &["freedom", "liberty"],
+            ),
+            tiv(
+                time("2077-07-07T12:00:00Z"),
+                time("2077-07-07T15:00:00Z"),
+                &["freedom"],
+            ),
+            tiv(
+                time("2077-07-07T15:00:00Z"),
+                time("2077-07-07T16:00:00Z"),
+                &["freedom"],
+            ),
+            tiv(
+                time("2077-07-07T14:00:00Z"),
+                time("2077-07-07T17:00:00Z"),
+                &["liberty"],
+            ),
+        ];
+        let expected: HashMap<String, Vec<(Time, Time)>> = vec![
+            (
+                "freedom".to_string(),
+                vec![(time("2077-07-07T09:00:00Z"), time("2077-07-07T16:00:00Z"))],
+            ),
+            (
+                "liberty".to_string(),
+                vec![
+                    (time("2077-07-07T09:00:00Z"), time("2077-07-07T13:00:00Z")),
+                    (time("2077-07-07T14:00:00Z"), time("2077-07-07T17:00:00Z")),
+                ],
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(flatten_per_tag(&history), expected);
+    }
+
+    #[test]
+    fn covered_points_works() {
+        let history = vec![
+            tiv(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T13:00:00Z"),
+                &["freedom", "liberty"],
+            ),
+            tiv(
+                time("2077-07-07T12:00:00Z"),
+                time("2077-07-07T17:00:00Z"),
+                &["freedom"],
+            ),
+        ];
+        let points = vec![
+            time("2077-07-07T17:00:00Z"),
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T14:00:00Z"),
+            time("2077-07-07T08:59:59Z"),
+            time("2077-07-07T12:30:00Z"),
+        ];
+
+        assert_eq!(
+            covered_points(&history, &points, &tags(&["freedom"])),
+            vec![false, true, true, false, true]
+        );
+        assert_eq!(
+            covered_points(&history, &points, &tags(&["freedom", "liberty"])),
+            vec![false, true, false, false, true]
+        );
+    }
+
+    #[test]
+    fn nearest_covered_works() {
+        let history = vec![
+            tiv(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T11:00:00Z"),
+                &["freedom"],
+            ),
+            tiv(
+                time("2077-07-07T10:00:00Z"),
+                time("2077-07-07T12:00:00Z"),
+                &["freedom"],
+            ),
+            tiv(
+                time("2077-07-07T15:00:00Z"),
+                time("2077-07-07T17:00:00Z"),
+                &["freedom", "liberty"],
+            ),
+        ];
+        let cases = vec![
+            (
+                "inside",
+                "2077-07-07T11:30:00Z",
+                "freedom",
+                Some(("2077-07-07T09:00:00Z", "2077-07-07T12:00:00Z")),
+            ),
+            (
+                "closer before",
+                "2077-07-07T13:00:00Z",
+                "freedom",
+                Some(("2077-07-07T09:00:00Z", "2077-07-07T12:00:00Z")),
+            ),
+            (
+                "tie",
+                "2077-07-07T13:30:00Z",
+                "freedom",
+                Some(("2077-07-07T09:00:00Z", "2077-07-07T12:00:00Z")),
+            ),
+            (
+                "closer after",
+                "2077-07-07T14:00:00Z",
+                "freedom",
+                Some(("2077-07-07T15:00:00Z", "2077-07-07T17:00:00Z")),
+            ),
+            (
+                "other tag",
+                "2077-07-07T10:00:00Z",
+                "liberty",
+                Some(("2077-07-07T15:00:00Z", "2077-07-07T17:00:00Z")),
+            ),
+            ("unknown tag", "2077-07-07T10:00:00Z", "fairness", None),
+        ];
+
+        for (name, point, tag, expected) in cases {
+            assert_eq!(
+                nearest_covered(&history, time(point), tag),
+                expected.map(|(l, u)| (time(l), time(u))),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn longest_gap_works() {
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            &["freedom", "liberty"],
+        );
+        let history = vec![
+            tiv(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T10:00:00Z"),
+                &["freedom"],
+            ),
+            tiv(
+                time("2077-07-07T12:00:00Z"),
+                time("2077-07-07T13:00:00Z"),
+                &["liberty"],
+            ),
+            tiv(
+                time("2077-07-07T14:00:00Z"),
+                time("2077-07-07T17:00:00Z"),
+                &["freedom", "liberty"],
+            ),
+        ];
+        let expected_per_tag: HashMap<String, (Time, Time)> = vec![
+            (
+                "freedom".to_string(),
+                (time("2077-07-07T10:00:00Z"), time("2077-07-07T14:00:00Z")),
+            ),
+            (
+                "liberty".to_string(),
+                (time("2077-07-07T09:00:00Z"), time("2077-07-07T12:00:00Z")),
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(specified.longest_gap_per_tag(&history), expected_per_tag);
+        assert_eq!(
+            specified.longest_gap(&history),
+            Some(tiv(
+                time("2077-07-07T10:00:00Z"),
+                time("2077-07-07T14:00:00Z"),
+                &["freedom"],
+            ))
+        );
+        assert_eq!(
+            specified.longest_gap(std::slice::from_ref(&specified)),
+            None
+        );
+    }
+
+    #[test]
+    fn drop_short_entries_works() {
+        let history = vec![
+            tiv(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T17:00:00Z"),
+                &["freedom"],
+            ),
+            tiv(
+                time("2077-07-07T10:00:00.000Z"),
+                time("2077-07-07T10:00:00.003Z"),
+                &["liberty"],
+            ),
+            tiv(
+                time("2077-07-07T11:00:00Z"),
+                time("2077-07-07T11:00:01Z"),
+                &["liberty"],
+            ),
+        ];
+        let mut kept = history.clone();
+        let dropped = drop_short_entries(&mut kept, chrono::Duration::seconds(1));
+
+        assert_eq!(kept, vec![history[0].clone(), history[2].clone()]);
+        assert_eq!(dropped, vec![history[1].clone()]);
+    }
+
+    #[test]
+    fn clip_history_works() {
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            &["freedom", "liberty"],
+        );
+        let history = vec![
+            tiv(
+                time("2077-07-07T07:00:00Z"),
+                time("2077-07-07T09:00:00Z"),
+                &["freedom"],
+            ),
+            tiv(
+                time("2077-07-07T08:00:00Z"),
+                time("2077-07-07T10:00:00Z"),
+                &["freedom"],
+            ),
+            tiv(
+                time("2077-07-07T12:00:00Z"),
+                time("2077-07-07T13:00:00Z"),
+                &["liberty"],
+            ),
+            tiv(
+                time("2077-07-07T16:00:00Z"),
+                time("2077-07-08T16:00:00Z"),
+                &["liberty"],
+            ),
+        ];
+        let clipped = clip_history(&specified, &history);
+
+        assert_eq!(
+            clipped,
+            vec![
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T10:00:00Z"),
+                    &["freedom"],
+                ),
+                history[2].clone(),
+                tiv(
+                    time("2077-07-07T16:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["liberty"],
+                ),
+            ]
+        );
+        assert_eq!(
+            specified.clone().difference(clipped),
+            specified.difference(history)
+        );
+    }
+
+    #[test]
+    fn difference_multi_works() {
+        let windows = vec![
+            tiv(
+                time("2077-07-08T09:00:00Z"),
+                time("2077-07-08T17:00:00Z"),
+                &["freedom"],
+            ),
+            tiv(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T17:00:00Z"),
+                &["freedom"],
+            ),
+        ];
+        let history = vec![tiv(
+            time("2077-07-07T12:00:00Z"),
+            time("2077-07-08T12:00:00Z"),
+            &["freedom"],
+        )];
+
+        assert_eq!(
+            TaggedInterval::difference_multi(&windows, &history),
+            vec![
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T12:00:00Z"),
+                    &["freedom"],
+                ),
+                tiv(
+                    time("2077-07-08T12:00:00Z"),
+                    time("2077-07-08T17:00:00Z"),
+                    &["freedom"],
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn difference_excluding_works() {
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            &["freedom", "liberty"],
+        );
+        let history = vec![tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T11:00:00Z"),
+            &["freedom"],
+        )];
+        let blackouts = vec![
+            (time("2077-07-07T12:00:00Z"), time("2077-07-07T13:00:00Z")),
+            (time("2077-07-07T16:00:00Z"), time("2077-07-07T18:00:00Z")),
+        ];
+
+        assert_eq!(
+            specified.difference_excluding(history, &blackouts),
+            vec![
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T11:00:00Z"),
+                    &["liberty"],
+                ),
+                tiv(
+                    time("2077-07-07T11:00:00Z"),
+                    time("2077-07-07T12:00:00Z"),
+                    &["freedom", "liberty"],
+                ),
+                tiv(
+                    time("2077-07-07T13:00:00Z"),
+                    time("2077-07-07T16:00:00Z"),
+                    &["freedom", "liberty"],
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn difference_with_tolerance_works() {
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T10:00:00Z"),
+            &["tick", "daily"],
+        );
+        let history = vec![
+            tiv(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T09:30:00Z"),
+                &["tick", "daily"],
+            ),
+            tiv(
+                time("2077-07-07T09:30:01Z"),
+                time("2077-07-07T09:45:00Z"),
+                &["tick", "daily"],
+            ),
+            tiv(
+                time("2077-07-07T09:45:30Z"),
+                time("2077-07-07T10:00:00Z"),
+                &["tick", "daily"],
+            ),
+        ];
+        let tolerances: HashMap<String, chrono::Duration> =
+            vec![("tick".to_string(), chrono::Duration::seconds(5))]
+                .into_iter()
+                .collect();
+
+        assert_eq!(
+            specified.difference_with_tolerance(history, &tolerances),
+            vec![
+                tiv(
+                    time("2077-07-07T09:30:00Z"),
+                    time("2077-07-07T09:30:01Z"),
+                    &["daily"],
+                ),
+                tiv(
+                    time("2077-07-07T09:45:00Z"),
+                    time("2077-07-07T09:45:30Z"),
+                    &["tick", "daily"],
+                ),
+            ]
+        );
+
+        // Ranges left with the same tags join.
+        let specified = TaggedInterval::new(0i64, 10, tags(&["tick", "daily"]));
+        let history = vec![TaggedInterval::new(3, 10, tags(&["tick"]))];
+        let tolerances: HashMap<String, i64> = vec![("tick".to_string(), 5)].into_iter().collect();
+        assert_eq!(
+            specified.difference_with_tolerance(history, &tolerances),
+            vec![TaggedInterval::new(0, 10, tags(&["daily"]))]
+        );
+    }
+
+    #[test]
+    fn difference_works_with_non_copy_bounds() {
+        let version = |s: &str| s.to_string();
+        let specified = TaggedInterval::new(version("v1"), version("v5"), tags(&["freedom"]));
+        let history = vec![
+            TaggedInterval::new(version("v1"), version("v2"), tags(&["freedom"])),
+            TaggedInterval::new(version("v3"), version("v4"), tags(&["freedom"])),
+        ];
+
+        assert_eq!(
+            specified.difference(history),
+            vec![
+                TaggedInterval::new(version("v2"), version("v3"), tags(&["freedom"])),
+                TaggedInterval::new(version("v4"), version("v5"), tags(&["freedom"])),
+            ]
+        );
+    }
+
+    #[test]
+    fn difference_descending_works() {
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            &["freedom", "liberty"],
+        );
+        let history = vec![
+            tiv(
+                time("2077-07-07T10:00:00Z"),
+                time("2077-07-07T12:00:00Z"),
+                &["freedom"],
+            ),
+            tiv(
+                time("2077-07-07T14:00:00Z"),
+                time("2077-07-07T17:00:00Z"),
+                &["freedom", "liberty"],
+            ),
+        ];
+        let mut expected = specified.clone().difference(history.clone());
+        expected.reverse();
+
+        assert_eq!(specified.difference_descending(history), expected);
+    }
+
+    #[test]
+    fn excess_works() {
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            &["freedom"],
+        );
+        let history = vec![
+            tiv(
+                time("2077-07-07T08:00:00Z"),
+                time("2077-07-07T12:00:00Z"),
+                &["freedom", "liberty"],
+            ),
+            tiv(
+                time("2077-07-07T12:00:00Z"),
+                time("2077-07-07T17:00:00Z"),
+                &["freedom"],
+            ),
+            tiv(
+                time("2077-07-07T18:00:00Z"),
+                time("2077-07-07T19:00:00Z"),
+                &["freedom"],
+            ),
+        ];
+
+        assert_eq!(
+            specified.excess(history),
+            vec![
+                tiv(
+                    time("2077-07-07T08:00:00Z"),
+                    time("2077-07-07T09:00:00Z"),
+                    &["freedom", "liberty"],
+                ),
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T12:00:00Z"),
+                    &["liberty"],
+                ),
+                tiv(
+                    time("2077-07-07T18:00:00Z"),
+                    time("2077-07-07T19:00:00Z"),
+                    &["freedom"],
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn difference_from_predicate_works() {
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            &["freedom", "liberty"],
+        );
+        let horizon = time("2077-07-07T12:00:00Z");
+        let retained = Predicate(|lower: &Time, upper: &Time, tag: &str| {
+            if tag != "freedom" || *lower >= horizon {
+                CoverageAnswer::Uncovered
+            } else if *upper <= horizon {
+                CoverageAnswer::Covered
+            } else {
+                CoverageAnswer::SplitAt(vec![horizon])
+            }
+        });
+
+        assert_eq!(
+            specified.difference_from(&retained),
+            vec![
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T12:00:00Z"),
+                    &["liberty"],
+                ),
+                tiv(
+                    time("2077-07-07T12:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["freedom", "liberty"],
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn intervals_from_points_works() {
+        let points = vec![
+            (time("2077-07-07T09:00:00Z"), "freedom".to_string()),
+            (time("2077-07-07T09:00:30Z"), "freedom".to_string()),
+            (time("2077-07-07T09:01:00Z"), "freedom".to_string()),
+            (time("2077-07-07T09:05:00Z"), "freedom".to_string()),
+            (time("2077-07-07T09:06:00Z"), "freedom".to_string()),
+            (time("2077-07-07T09:00:00Z"), "liberty".to_string()),
+            (time("2077-07-07T09:10:00Z"), "liberty".to_string()),
+        ];
+
+        assert_eq!(
+            intervals_from_points(points, chrono::Duration::minutes(1)),
+            vec![
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T09:01:00Z"),
+                    &["freedom"],
+                ),
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T09:00:00Z"),
+                    &["liberty"],
+                ),
+                tiv(
+                    time("2077-07-07T09:05:00Z"),
+                    time("2077-07-07T09:06:00Z"),
+                    &["freedom"],
+                ),
+                tiv(
+                    time("2077-07-07T09:10:00Z"),
+                    time("2077-07-07T09:10:00Z"),
+                    &["liberty"],
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn sessions_from_points_works() {
+        let points = vec![
+            (time("2077-07-07T09:00:00Z"), "freedom".to_string()),
+            (time("2077-07-07T09:00:30Z"), "freedom".to_string()),
+            (time("2077-07-07T09:01:00Z"), "freedom".to_string()),
+            (time("2077-07-07T09:01:30Z"), "freedom".to_string()),
+            (time("2077-07-07T09:05:00Z"), "freedom".to_string()),
+            (time("2077-07-07T09:05:30Z"), "freedom".to_string()),
+            (time("2077-07-07T09:00:00Z"), "liberty".to_string()),
+            (time("2077-07-07T09:01:00Z"), "liberty".to_string()),
+        ];
+
+        assert_eq!(
+            sessions_from_points(
+                points,
+                chrono::Duration::minutes(1),
+                chrono::Duration::minutes(1)
+            ),
+            vec![
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T09:01:30Z"),
+                    &["freedom"],
+                ),
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T09:01:00Z"),
+                    &["liberty"],
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn downsample_works() {
+        let history = vec![
+            tiv(
+                time("2077-07-07T09:00:30Z"),
+                time("2077-07-07T09:02:15Z"),
+                &["freedom"],
+            ),
+            tiv(
+                time("2077-07-07T09:05:10Z"),
+                time("2077-07-07T09:05:50Z"),
+                &["liberty"],
+            ),
+        ];
+        let minute = chrono::Duration::minutes(1);
+
+        assert_eq!(
+            downsample(&history, minute, Rounding::Inward),
+            vec![tiv(
+                time("2077-07-07T09:01:00Z"),
+                time("2077-07-07T09:02:00Z"),
+                &["freedom"],
+            )]
+        );
+        assert_eq!(
+            downsample(&history, minute, Rounding::Outward),
+            vec![
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T09:03:00Z"),
+                    &["freedom"],
+                ),
+                tiv(
+                    time("2077-07-07T09:05:00Z"),
+                    time("2077-07-07T09:06:00Z"),
+                    &["liberty"],
+                ),
+            ]
+        );
+        assert_eq!((-7i64).snap_down(5), -10);
+        assert_eq!((-7i64).snap_up(5), -5);
+    }
+
+    #[test]
+    fn difference_with_tie_break_works() {
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            &["freedom", "liberty"],
+        );
+        let history = vec![
+            tiv(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T12:00:00Z"),
+                &["freedom"],
+            ),
+            tiv(
+                time("2077-07-07T12:00:00Z"),
+                time("2077-07-07T17:00:00Z"),
+                &["freedom"],
+            ),
+        ];
+
+        let joined = vec![tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            &["liberty"],
+        )];
+        let split = vec![
+            tiv(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T12:00:00Z"),
+                &["liberty"],
+            ),
+            tiv(
+                time("2077-07-07T12:00:00Z"),
+                time("2077-07-07T17:00:00Z"),
+                &["liberty"],
+            ),
+        ];
+        let table = vec![
+            (TieBreak::UpperFirst, joined),
+            (TieBreak::LowerFirst, split),
+        ];
+
+        for (tie_break, expected) in table {
+            // Touching entries with tags that are not specified leave no seam.
+            let mut history = history.clone();
+            history.extend(vec![
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T14:00:00Z"),
+                    &["fairness"],
+                ),
+                tiv(
+                    time("2077-07-07T14:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["fairness"],
+                ),
+            ]);
+            assert_eq!(
+                specified
+                    .clone()
+                    .difference_with_tie_break(history, tie_break),
+                expected,
+                "{:?}",
+                tie_break
+            );
+        }
+    }
+
+    #[test]
+    fn difference_with_points_works() {
+        let day = |d: i64, tags_: &[&str]| TaggedInterval::new(d, d, tags(tags_));
+        let specified = TaggedInterval::new(1i64, 5, tags(&["freedom", "liberty"]));
+        let history = vec![
+            day(2, &["freedom", "liberty"]),
+            day(3, &["freedom"]),
+            TaggedInterval::new(4, 6, tags(&["freedom"])),
+        ];
+
+        assert_eq!(
+            specified.difference_with_points(history),
+            vec![
+                TaggedInterval::new(1, 2, tags(&["freedom", "liberty"])),
+                TaggedInterval::new(3, 5, tags(&["liberty"])),
+            ]
+        );
+
+        let specified = TaggedInterval::new(u8::MAX - 1, u8::MAX, tags(&["freedom"]));
+        assert_eq!(
+            specified
+                .clone()
+                .difference_with_points(vec![TaggedInterval::new(
+                    u8::MAX,
+                    u8::MAX,
+                    tags(&["freedom"])
+                ),]),
+            vec![specified]
+        );
+
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T09:00:00.000000002Z"),
+            &["freedom"],
+        );
+        let instant = time("2077-07-07T09:00:00.000000001Z");
+        assert_eq!(
+            specified.difference_with_points(vec![tiv(instant, instant, &["freedom"])]),
+            vec![tiv(time("2077-07-07T09:00:00Z"), instant, &["freedom"])]
+        );
+    }
+
+    #[test]
+    fn difference_with_zero_length_works() {
+        let specified = TaggedInterval::new(0i64, 10, tags(&["freedom"]));
+        let history = vec![
+            TaggedInterval::new(0, 4, tags(&["freedom"])),
+            TaggedInterval::new(6, 6, tags(&["freedom"])),
+        ];
+
+        let table = vec![
+            (
+                ZeroLength::Ignore,
+                Ok(vec![TaggedInterval::new(4, 10, tags(&["freedom"]))]),
+            ),
+            (
+                ZeroLength::CoverPoint,
+                Ok(vec![
+                    TaggedInterval::new(4, 6, tags(&["freedom"])),
+                    TaggedInterval::new(7, 10, tags(&["freedom"])),
+                ]),
+            ),
+            (
+                ZeroLength::Error,
+                Err(ZeroLengthError { index: 1, bound: 6 }),
+            ),
+        ];
+
+        for (policy, expected) in table {
+            assert_eq!(
+                specified
+                    .clone()
+                    .difference_with_zero_length(history.clone(), policy)
+                    .map(Difference::into_vec),
+                expected,
+                "{:?}",
+                policy
+            );
+        }
+        assert_eq!(
+            specified.difference(history),
+            vec![TaggedInterval::new(4, 10, tags(&["freedom"]))]
+        );
+    }
+
+    #[test]
+    fn try_difference_works() {
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            &["freedom"],
+        );
+        let history = vec![
+            tiv(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T13:00:00Z"),
+                &["freedom"],
+            ),
+            tiv(
+                time("2077-07-07T15:00:00Z"),
+                time("2077-07-07T15:00:00Z"),
+                &["freedom"],
+            ),
+        ];
+
+        assert_eq!(
+            specified
+                .clone()
+                .try_difference(history[..1].to_vec())
+                .map(Difference::into_vec),
+            Ok(vec![tiv(
+                time("2077-07-07T13:00:00Z"),
+                time("2077-07-07T17:00:00Z"),
+                &["freedom"],
+            )])
+        );
+        assert_eq!(
+            specified.try_difference(history).map(Difference::into_vec),
+            Err(ZeroLengthError {
+                index: 1,
+                bound: time("2077-07-07T15:00:00Z"),
+            })
+        );
+    }
+
+    #[test]
+    fn difference_goals_works() {
+        let history = vec![
+            TaggedInterval::new(0i64, 10, tags(&["freedom"])),
+            TaggedInterval::new(5, 20, tags(&["liberty"])),
+        ];
+        let goals = vec![
+            (
+                "morning",
+                TaggedInterval::new(0, 10, tags(&["freedom", "liberty"])),
+            ),
+            ("evening", TaggedInterval::new(10, 30, tags(&["liberty"]))),
+            ("night", TaggedInterval::new(30, 40, tags(&["freedom"]))),
+        ];
+        let results = TaggedInterval::difference_goals(&goals, &history);
+
+        assert_eq!(results.len(), 3);
+        for (key, goal) in goals.iter() {
+            assert_eq!(
+                results[key],
+                goal.clone().difference(history.clone()),
+                "{}",
+                key
+            );
+        }
+        assert_eq!(
+            results["evening"],
+            vec![TaggedInterval::new(20, 30, tags(&["liberty"]))]
+        );
+    }
+
+    #[test]
+    fn difference_by_key_works() {
+        let offset = |partition: &str, offset: i64| (partition.to_string(), offset);
+        let specified = TaggedInterval::new(offset("p0", 0), offset("p1", 0), tags(&["freedom"]));
+        let history = vec![
+            TaggedInterval::new(offset("p0", 0), offset("p0", 50), tags(&["freedom"])),
+            TaggedInterval::new(offset("p0", 80), offset("p1", 0), tags(&["freedom"])),
+        ];
+        let key =
+            |(partition, offset): &(String, i64)| (partition[1..].parse::<u32>().unwrap(), *offset);
+
+        assert_eq!(
+            specified.clone().difference_by_key(history.clone(), key),
+            specified.difference(history)
+        );
+    }
+
+    #[test]
+    fn goals_served_works() {
+        let goals = vec![
+            (
+                1,
+                TaggedInterval::new(0i64, 10, tags(&["freedom", "liberty"])),
+            ),
+            (2, TaggedInterval::new(10, 30, tags(&["liberty"]))),
+            (3, TaggedInterval::new(5, 15, tags(&["fairness"]))),
+            (4, TaggedInterval::new(20, 40, tags(&["freedom"]))),
+        ];
+        let entry = TaggedInterval::new(5, 20, tags(&["freedom", "liberty"]));
+
+        assert_eq!(entry.goals_served(&goals), vec![1, 2]);
+    }
+
+    #[test]
+    fn by_key_works() {
+        let intervals = vec![
+            TaggedInterval::new(0i64, 10, tags(&["customer-1", "customer-2"])),
+            TaggedInterval::new(5, 20, tags(&["customer-01"])),
+            TaggedInterval::new(0, 5, tags(&["shard-1"])),
+        ];
+        let customer = |tag: &str| tag.strip_prefix("customer-")?.parse::<u32>().ok();
+        let expected: HashMap<u32, Vec<(i64, i64)>> = vec![(1, vec![(0, 20)]), (2, vec![(0, 10)])]
+            .into_iter()
+            .collect();
+
+        assert_eq!(by_key(&intervals, customer), expected);
+    }
+
+    #[test]
+    fn difference_with_works() {
+        let specified = TaggedInterval::new(0i64, 100, tags(&["freedom"]));
+        let history: Vec<_> = (0..10)
+            .map(|i| TaggedInterval::new(i * 10, i * 10 + 5, tags(&["freedom"])))
+            .collect();
+        let mut gaps = vec![];
+
+        specified.clone().difference_with(history.clone(), |iv| {
+            gaps.push(iv);
+            if gaps.len() < 3 {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        });
+
+        assert_eq!(gaps, specified.difference(history)[..3].to_vec());
+    }
+
+    #[test]
+    fn union_works() {
+        let table = vec![
+            (vec![], vec![]),
+            (
+                vec![
+                    tiv(
+                        time("2077-07-07T09:00:00Z"),
+                        time("2077-07-07T12:00:00Z"),
+                        &["freedom"],
+                    ),
+                    tiv(
+                        time("2077-07-07T11:00:00Z"),
+                        time("2077-07-07T13:00:00Z"),
+                        &["liberty"],
+                    ),
+                ],
+                vec![
+                    tiv(
+                        time("2077-07-07T09:00:00Z"),
+                        time("2077-07-07T11:00:00Z"),
+                        &["freedom"],
+                    ),
+                    tiv(
+                        time("2077-07-07T11:00:00Z"),
+                        time("2077-07-07T12:00:00Z"),
+                        &["freedom", "liberty"],
+                    ),
+                    tiv(
+                        time("2077-07-07T12:00:00Z"),
+                        time("2077-07-07T13:00:00Z"),
+                        &["liberty"],
+                    ),
+                ],
+            ),
+            (
+                vec![
+                    tiv(
+                        time("2077-07-07T09:00:00Z"),
+                        time("2077-07-07T10:00:00Z"),
+                        &["freedom"],
+                    ),
+                    tiv(
+                        time("2077-07-07T12:00:00Z"),
+                        time("2077-07-07T13:00:00Z"),
+                        &["freedom"],
+                    ),
+                    tiv(
+                        time("2077-07-07T10:00:00Z"),
+                        time("2077-07-07T11:00:00Z"),
+                        &["freedom"],
+                    ),
+                ],
+                vec![
+                    tiv(
+                        time("2077-07-07T09:00:00Z"),
+                        time("2077-07-07T11:00:00Z"),
+                        &["freedom"],
+                    ),
+                    tiv(
+                        time("2077-07-07T12:00:00Z"),
+                        time("2077-07-07T13:00:00Z"),
+                        &["freedom"],
+                    ),
+                ],
+            ),
+        ];
+
+        for (intervals, expected) in table {
+            assert_eq!(
+                TaggedInterval::union_all(intervals.clone()),
+                expected,
+                "{:?}",
+                intervals
+            );
+        }
+
+        let x = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T10:00:00Z"),
+            &["freedom"],
+        );
+        let y = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T10:00:00Z"),
+            &["liberty"],
+        );
+        assert_eq!(
+            x.union(y),
+            vec![tiv(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T10:00:00Z"),
+                &["freedom", "liberty"],
+            )]
+        );
+    }
+
+    #[test]
+    fn intersection_works() {
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            &["freedom", "liberty"],
+        );
+        let history = vec![
+            tiv(
+                time("2077-07-07T08:00:00Z"),
+                time("2077-07-07T12:00:00Z"),
+                &["freedom", "democracy"],
+            ),
+            tiv(
+                time("2077-07-07T11:00:00Z"),
+                time("2077-07-07T13:00:00Z"),
+                &["liberty"],
+            ),
+        ];
+
+        let covered = specified.clone().intersection(&history);
+        assert_eq!(
+            covered,
+            vec![
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T11:00:00Z"),
+                    &["freedom"],
+                ),
+                tiv(
+                    time("2077-07-07T11:00:00Z"),
+                    time("2077-07-07T12:00:00Z"),
+                    &["freedom", "liberty"],
+                ),
+                tiv(
+                    time("2077-07-07T12:00:00Z"),
+                    time("2077-07-07T13:00:00Z"),
+                    &["liberty"],
+                ),
+            ]
+        );
+
+        // Together with the difference, it accounts for the whole window.
+        let mut both = covered;
+        both.extend(specified.clone().difference(history));
+        assert_eq!(TaggedInterval::union_all(both), vec![specified]);
+    }
+
+    #[test]
+    fn intersection_skips_unspecified_tags() {
+        let specified = tiv(
+            time("2077-07-07T09:00:00Z"),
+            time("2077-07-07T17:00:00Z"),
+            &["freedom", "liberty", "fairness"],
+        );
+        let history = vec![
+            tiv(
+                time("2077-07-07T08:00:00Z"),
+                time("2077-07-07T12:00:00Z"),
+                &["freedom", "liberty"],
+            ),
+            tiv(
+                time("2077-07-07T11:00:00Z"),
+                time("2077-07-07T13:00:00Z"),
+                &["democracy"],
+            ),
+            tiv(
+                time("2077-07-07T15:00:00Z"),
+                time("2077-07-07T18:00:00Z"),
+                &["liberty", "fairness"],
+            ),
+        ];
+
+        assert_eq!(
+            specified.intersection(&history),
+            vec![
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T12:00:00Z"),
+                    &["freedom", "liberty"],
+                ),
+                tiv(
+                    time("2077-07-07T15:00:00Z"),
+                    time("2077-07-07T17:00:00Z"),
+                    &["liberty", "fairness"],
+                ),
+            ]
+        );
+    }
 }
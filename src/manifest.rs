@@ -0,0 +1,69 @@
+use crate::TaggedInterval;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use std::collections::HashSet;
+
+/// Parses an object key laid out as `<topic>/<YYYY>/<MM>/<DD>/<HHMM>-<HHMM>.<ext>`
+/// into an interval in UTC tagged by topic, which may itself contain `/`. An
+/// end not after the start is taken to be on the next day.
+pub fn parse_key(key: &str) -> Option<TaggedInterval<DateTime<Utc>>> {
+    let mut parts = key.rsplitn(5, '/');
+    let file = parts.next()?;
+    let day = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let year = parts.next()?.parse().ok()?;
+    let topic = parts.next().filter(|t| !t.is_empty())?;
+
+    let date = NaiveDate::from_ymd_opt(year, month, day)?;
+    let (stem, _) = file.split_once('.')?;
+    let (from, to) = stem.split_once('-')?;
+    let time = |s: &str| NaiveTime::parse_from_str(s, "%H%M").ok();
+    let lower = Utc.from_utc_datetime(&date.and_time(time(from)?));
+    let mut upper = Utc.from_utc_datetime(&date.and_time(time(to)?));
+    if upper <= lower {
+        upper += Duration::days(1);
+    }
+    let tags: HashSet<String> = [topic.to_string()].iter().cloned().collect();
+    Some(TaggedInterval::new(lower, upper, tags))
+}
+
+/// Builds history from an object listing, skipping keys that do not follow
+/// the layout of `parse_key`.
+pub fn from_keys<'a, I>(keys: I) -> Vec<TaggedInterval<DateTime<Utc>>>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    keys.into_iter().filter_map(parse_key).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{time, tiv};
+
+    #[test]
+    fn from_keys_works() {
+        let keys = vec![
+            "freedom/2077/07/07/0900-1000.parquet",
+            "raw/liberty/2077/07/07/2330-0000.json.gz",
+            "freedom/2077/07/07/_SUCCESS",
+            "freedom/2077/02/30/0900-1000.parquet",
+            "2077/07/07/0900-1000.parquet",
+        ];
+
+        assert_eq!(
+            from_keys(keys),
+            vec![
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T10:00:00Z"),
+                    &["freedom"]
+                ),
+                tiv(
+                    time("2077-07-07T23:30:00Z"),
+                    time("2077-07-08T00:00:00Z"),
+                    &["raw/liberty"]
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Import of history from object store listings, e.g. S3's ListObjectsV2.
+
+use crate::manifest::parse_key;
+use crate::TaggedInterval;
+use chrono::{DateTime, Utc};
+
+/// One page of a listing.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Page {
+    pub keys: Vec<String>,
+    /// The token to pass for the next page, if there is one.
+    pub next: Option<String>,
+}
+
+/// A store whose keys can be listed page by page.
+pub trait ObjectStore {
+    type Error;
+
+    /// Lists keys starting with `prefix`, continuing after the page that
+    /// returned `token`.
+    fn list(&self, prefix: &str, token: Option<&str>) -> Result<Page, Self::Error>;
+}
+
+/// Lists every key under `prefix` and builds history from those laid out
+/// as `manifest::parse_key` expects. Stops at the first failed request.
+pub fn import<S>(store: &S, prefix: &str) -> Result<Vec<TaggedInterval<DateTime<Utc>>>, S::Error>
+where
+    S: ObjectStore,
+{
+    let mut history = vec![];
+    let mut token = None;
+    loop {
+        let page = store.list(prefix, token.as_deref())?;
+        history.extend(page.keys.iter().filter_map(|key| parse_key(key)));
+        match page.next {
+            Some(next) => token = Some(next),
+            None => return Ok(history),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{time, tiv};
+
+    /// Serves keys two per page, with the index of the next key as token.
+    struct Pages(Vec<&'static str>);
+
+    impl ObjectStore for Pages {
+        type Error = String;
+
+        fn list(&self, prefix: &str, token: Option<&str>) -> Result<Page, String> {
+            let keys: Vec<&str> = self
+                .0
+                .iter()
+                .filter(|k| k.starts_with(prefix))
+                .cloned()
+                .collect();
+            let start = match token {
+                Some(token) => token.parse().map_err(|_| format!("bad token {}", token))?,
+                None => 0,
+            };
+            let end = (start + 2).min(keys.len());
+            Ok(Page {
+                keys: keys[start..end].iter().map(|k| k.to_string()).collect(),
+                next: Some(end.to_string()).filter(|_| end < keys.len()),
+            })
+        }
+    }
+
+    #[test]
+    fn import_works() {
+        let store = Pages(vec![
+            "freedom/2077/07/07/0900-1000.parquet",
+            "freedom/2077/07/07/_SUCCESS",
+            "freedom/2077/07/07/1000-1100.parquet",
+            "liberty/2077/07/07/0900-1000.parquet",
+            "freedom/2077/07/08/0000-0100.parquet",
+        ]);
+
+        assert_eq!(
+            import(&store, "freedom/"),
+            Ok(vec![
+                tiv(
+                    time("2077-07-07T09:00:00Z"),
+                    time("2077-07-07T10:00:00Z"),
+                    &["freedom"]
+                ),
+                tiv(
+                    time("2077-07-07T10:00:00Z"),
+                    time("2077-07-07T11:00:00Z"),
+                    &["freedom"]
+                ),
+                tiv(
+                    time("2077-07-08T00:00:00Z"),
+                    time("2077-07-08T01:00:00Z"),
+                    &["freedom"]
+                ),
+            ])
+        );
+        assert_eq!(import(&store, "fairness/"), Ok(vec![]));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{BoundOps, TaggedInterval};
+use std::collections::HashSet;
+use std::thread;
+
+fn covered_tags<Bound, M>(
+    history: &[TaggedInterval<Bound, M>],
+    active: impl Fn(&TaggedInterval<Bound, M>) -> bool,
+) -> HashSet<&String>
+where
+    Bound: BoundOps,
+{
+    history
+        .iter()
+        .filter(|iv| active(iv))
+        .flat_map(|iv| iv.tags.iter())
+        .collect()
+}
+
+/// Computes `TaggedInterval::difference` by cutting `specified` into up to
+/// `partitions` windows at history bounds, sweeping the windows on separate
+/// threads and joining the pieces that meet at a cut without a coverage
+/// change.
+pub fn difference<Bound, M>(
+    specified: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+    partitions: usize,
+) -> Vec<TaggedInterval<Bound, M>>
+where
+    Bound: BoundOps + Send + Sync,
+    M: Clone + PartialEq + Send + Sync,
+{
+    let mut candidates: Vec<Bound> = history
+        .iter()
+        .flat_map(|iv| vec![iv.lower.clone(), iv.upper.clone()])
+        .filter(|b| specified.lower < *b && *b < specified.upper)
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+
+    let mut cuts = vec![specified.lower.clone()];
+    for k in 1..partitions.max(1) {
+        if let Some(cut) = candidates.get(k * candidates.len() / partitions) {
+            if cuts.last() != Some(cut) {
+                cuts.push(cut.clone());
+            }
+        }
+    }
+    cuts.push(specified.upper.clone());
+
+    let pieces: Vec<Vec<TaggedInterval<Bound, M>>> = thread::scope(|scope| {
+        let handles: Vec<_> = cuts
+            .windows(2)
+            .map(|window| {
+                let (lower, upper) = (window[0].clone(), window[1].clone());
+                scope.spawn(move || {
+                    let history: Vec<_> = history
+                        .iter()
+                        .filter(|iv| iv.lower < upper && lower < iv.upper)
+                        .cloned()
+                        .collect();
+                    let window = TaggedInterval {
+                        lower,
+                        upper,
+                        tags: specified.tags.clone(),
+                        meta: specified.meta.clone(),
+                    };
+                    window.difference(history).into_vec()
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let mut result: Vec<TaggedInterval<Bound, M>> = vec![];
+    for (piece, cut) in pieces.into_iter().zip(cuts.iter()) {
+        let mut piece = piece.into_iter().peekable();
+        if let (Some(last), Some(first)) = (result.last_mut(), piece.peek()) {
+            let before = covered_tags(history, |iv| iv.lower < *cut && *cut <= iv.upper);
+            let after = covered_tags(history, |iv| iv.lower <= *cut && *cut < iv.upper);
+            if last.upper == *cut && first.lower == *cut && before == after {
+                last.upper = first.upper.clone();
+                piece.next();
+            }
+        }
+        result.extend(piece);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{random_case, Lcg};
+
+    #[test]
+    fn difference_matches_sweep() {
+        let mut rng = Lcg(11);
+        for _ in 0..300 {
+            let (specified, history) = random_case(&mut rng, 50, 12);
+            let partitions = 1 + rng.next(5) as usize;
+
+            assert_eq!(
+                difference(&specified, &history, partitions),
+                specified.clone().difference(history.clone()),
+                "{:?} {:?} {}",
+                specified,
+                history,
+                partitions
+            );
+        }
+    }
+}
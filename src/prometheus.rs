@@ -0,0 +1,89 @@
+use crate::range_query::escape;
+use crate::report::per_tag_spans;
+use crate::{BoundOps, Length, TaggedInterval};
+use std::fmt::Write;
+use std::ops::Sub;
+
+/// Renders per-tag gauges of the coverage of `window` by `history` in the
+/// Prometheus text exposition format. Lengths are reported via
+/// `Length::as_f64`, i.e. in seconds for `chrono::Duration`.
+pub fn exposition<Bound, M, D>(
+    window: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+) -> String
+where
+    Bound: BoundOps + Sub<Output = D>,
+    M: Clone + PartialEq,
+    D: Length,
+{
+    let total = (window.upper.clone() - window.lower.clone()).as_f64();
+    let length = |(l, u): &(Bound, Bound)| (u.clone() - l.clone()).as_f64();
+    let tags = per_tag_spans(window, history);
+
+    let rows: Vec<(String, [f64; 3])> = tags
+        .into_iter()
+        .map(|(tag, covered, gaps)| {
+            let covered = covered.iter().map(length).fold(0.0, |a, b| a + b);
+            let ratio = if total > 0.0 { covered / total } else { 0.0 };
+            let largest = gaps.iter().map(length).fold(0.0, f64::max);
+            (tag, [ratio, gaps.len() as f64, largest])
+        })
+        .collect();
+    let metrics = [
+        (
+            "tagged_interval_coverage_ratio",
+            "Covered fraction of the window.",
+        ),
+        (
+            "tagged_interval_gap_count",
+            "Number of missing ranges in the window.",
+        ),
+        (
+            "tagged_interval_largest_gap_seconds",
+            "Length of the longest missing range in the window.",
+        ),
+    ];
+
+    let mut out = String::new();
+    for (i, (name, help)) in metrics.iter().enumerate() {
+        writeln!(out, "# HELP {} {}", name, help).unwrap();
+        writeln!(out, "# TYPE {} gauge", name).unwrap();
+        for (tag, values) in rows.iter() {
+            writeln!(out, "{}{{tag=\"{}\"}} {}", name, escape(tag), values[i]).unwrap();
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiv;
+
+    #[test]
+    fn exposition_works() {
+        let window = tiv(0, 100, &["freedom", "lib\"erty"]);
+        let history = vec![tiv(0, 25, &["freedom"]), tiv(50, 90, &["freedom"])];
+
+        assert_eq!(
+            exposition(&window, &history),
+            [
+                "# HELP tagged_interval_coverage_ratio Covered fraction of the window.",
+                "# TYPE tagged_interval_coverage_ratio gauge",
+                "tagged_interval_coverage_ratio{tag=\"freedom\"} 0.65",
+                "tagged_interval_coverage_ratio{tag=\"lib\\\"erty\"} 0",
+                "# HELP tagged_interval_gap_count Number of missing ranges in the window.",
+                "# TYPE tagged_interval_gap_count gauge",
+                "tagged_interval_gap_count{tag=\"freedom\"} 2",
+                "tagged_interval_gap_count{tag=\"lib\\\"erty\"} 1",
+                "# HELP tagged_interval_largest_gap_seconds \
+                 Length of the longest missing range in the window.",
+                "# TYPE tagged_interval_largest_gap_seconds gauge",
+                "tagged_interval_largest_gap_seconds{tag=\"freedom\"} 25",
+                "tagged_interval_largest_gap_seconds{tag=\"lib\\\"erty\"} 100",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{downsample, BoundOps, Difference, Rounding, Snap, TaggedInterval};
+use std::collections::HashMap;
+use std::ops::Sub;
+
+/// A difference with options, built up step by step and computed by `run`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DifferenceQuery<Bound, D, M = ()>
+where
+    Bound: BoundOps,
+{
+    specified: TaggedInterval<Bound, M>,
+    min_gap: Option<D>,
+    grid: Option<D>,
+    blackouts: Vec<(Bound, Bound)>,
+}
+
+impl<Bound, D, M> DifferenceQuery<Bound, D, M>
+where
+    Bound: BoundOps,
+    M: Clone + PartialEq,
+{
+    pub fn new(specified: TaggedInterval<Bound, M>) -> Self {
+        Self {
+            specified,
+            min_gap: None,
+            grid: None,
+            blackouts: vec![],
+        }
+    }
+
+    /// Ignores gaps shorter than `min_gap`, as `difference_with_tolerance`
+    /// does for every tag.
+    pub fn min_gap(mut self, min_gap: D) -> Self {
+        self.min_gap = Some(min_gap);
+        self
+    }
+
+    /// Widens the missing ranges outward to multiples of `grid`, joining the
+    /// ones that come to overlap. The widened ranges are clipped to the
+    /// specified window, so its bounds need not be multiples of `grid`.
+    pub fn snap(mut self, grid: D) -> Self {
+        self.grid = Some(grid);
+        self
+    }
+
+    /// Asks only for those of the specified tags in `tags`.
+    pub fn only_tags(mut self, tags: &[&str]) -> Self {
+        self.specified.tags.retain(|t| tags.contains(&t.as_str()));
+        self
+    }
+
+    /// Treats `blackouts` as covered, as `difference_excluding` does.
+    pub fn excluding(mut self, blackouts: &[(Bound, Bound)]) -> Self {
+        self.blackouts.extend_from_slice(blackouts);
+        self
+    }
+
+    pub fn run(self, history: &[TaggedInterval<Bound, M>]) -> Difference<Bound, M>
+    where
+        Bound: Sub<Output = D> + Snap<D>,
+        D: Copy + Ord,
+    {
+        let Self {
+            specified,
+            min_gap,
+            grid,
+            blackouts,
+        } = self;
+        let mut history = history.to_vec();
+        history.extend(blackouts.into_iter().map(|(lower, upper)| TaggedInterval {
+            lower,
+            upper,
+            tags: specified.tags.clone(),
+            meta: None,
+        }));
+        let missing = match min_gap {
+            Some(min_gap) => {
+                let tolerances: HashMap<String, D> = specified
+                    .tags
+                    .iter()
+                    .map(|t| (t.clone(), min_gap))
+                    .collect();
+                specified
+                    .clone()
+                    .difference_with_tolerance(history, &tolerances)
+            }
+            None => specified.clone().difference(history),
+        };
+        match grid {
+            Some(grid) => specified
+                .intersection(&downsample(&missing, grid, Rounding::Outward))
+                .into(),
+            None => missing,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiv;
+
+    #[test]
+    fn query_works() {
+        let specified = tiv(0, 100, &["freedom", "liberty", "fairness"]);
+        let history = vec![tiv(0, 40, &["freedom"]), tiv(43, 95, &["freedom"])];
+
+        assert_eq!(
+            DifferenceQuery::new(specified.clone()).run(&history),
+            specified.clone().difference(history.clone())
+        );
+        assert_eq!(
+            DifferenceQuery::new(specified.clone())
+                .only_tags(&["freedom"])
+                .run(&history),
+            vec![tiv(40, 43, &["freedom"]), tiv(95, 100, &["freedom"])]
+        );
+        assert_eq!(
+            DifferenceQuery::new(specified.clone())
+                .only_tags(&["freedom", "unknown"])
+                .min_gap(4)
+                .snap(10)
+                .run(&history),
+            vec![tiv(90, 100, &["freedom"])]
+        );
+        assert_eq!(
+            DifferenceQuery::new(specified)
+                .only_tags(&["liberty"])
+                .excluding(&[(0, 50)])
+                .snap(30)
+                .run(&history),
+            vec![tiv(30, 100, &["liberty"])]
+        );
+        assert_eq!(
+            DifferenceQuery::new(tiv(5, 95, &["freedom"]))
+                .snap(10)
+                .run(&[tiv(17, 85, &["freedom"])]),
+            vec![tiv(5, 20, &["freedom"]), tiv(80, 95, &["freedom"])]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! Import of Prometheus and VictoriaMetrics range query results.
+
+use crate::{intervals_from_points, BoundOps, TaggedInterval};
+use std::collections::BTreeMap;
+use std::ops::Sub;
+
+/// Escapes a label value as the Prometheus text format does.
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// One series of a range query result: its label set and the timestamps at
+/// which it had a sample.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Series<Bound> {
+    pub labels: BTreeMap<String, String>,
+    pub timestamps: Vec<Bound>,
+}
+
+/// Returns the tag `import` gives to series with `labels`, e.g.
+/// `{instance="a",job="api"}`.
+pub fn label_set_tag(labels: &BTreeMap<String, String>) -> String {
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape(value)))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+/// Turns range query results into coverage intervals tagged by label set,
+/// joining samples at most `max_gap` apart as `intervals_from_points` does.
+/// Series with the same label set are merged.
+pub fn import<Bound, D>(series: &[Series<Bound>], max_gap: D) -> Vec<TaggedInterval<Bound>>
+where
+    Bound: BoundOps + Sub<Output = D>,
+    D: Ord,
+{
+    let points = series.iter().flat_map(|s| {
+        let tag = label_set_tag(&s.labels);
+        s.timestamps.iter().map(move |t| (t.clone(), tag.clone()))
+    });
+    intervals_from_points(points, max_gap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiv;
+
+    #[test]
+    fn import_works() {
+        let labels = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+            pairs
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect()
+        };
+        let api = labels(&[("job", "api"), ("instance", "a")]);
+        let db = labels(&[("job", "d\"b")]);
+        let series = vec![
+            Series {
+                labels: api.clone(),
+                timestamps: vec![0i64, 15, 30, 90, 105],
+            },
+            Series {
+                labels: db,
+                timestamps: vec![0, 60],
+            },
+            Series {
+                labels: api,
+                timestamps: vec![45],
+            },
+        ];
+
+        assert_eq!(
+            import(&series, 15),
+            vec![
+                tiv(0, 45, &["{instance=\"a\",job=\"api\"}"]),
+                tiv(0, 0, &["{job=\"d\\\"b\"}"]),
+                tiv(60, 60, &["{job=\"d\\\"b\"}"]),
+                tiv(90, 105, &["{instance=\"a\",job=\"api\"}"]),
+            ]
+        );
+        assert_eq!(
+            import(&series, 60),
+            vec![
+                tiv(0, 105, &["{instance=\"a\",job=\"api\"}"]),
+                tiv(0, 60, &["{job=\"d\\\"b\"}"]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use crate::report::per_tag_spans;
+use crate::{BoundOps, Length, TaggedInterval};
+use std::env;
+use std::ops::Sub;
+
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+/// Returns the terminal width from `COLUMNS`, or 80.
+pub fn terminal_width() -> usize {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(80)
+}
+
+/// Renders one line per tag of `window` with a coverage bar and the covered
+/// ratio, fitting in `width` columns. Cells are green when fully covered,
+/// red when fully missing and yellow otherwise.
+pub fn term<Bound, M, D>(
+    window: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+    width: usize,
+) -> String
+where
+    Bound: BoundOps + Sub<Output = D>,
+    M: Clone + PartialEq,
+    D: Length,
+{
+    let tags = per_tag_spans(window, history);
+    let label = tags
+        .iter()
+        .map(|(t, _, _)| t.chars().count())
+        .max()
+        .unwrap_or(0);
+    let cells = width.saturating_sub(label + 9).max(1);
+    let total = (window.upper.clone() - window.lower.clone()).as_f64();
+    let offset = |b: &Bound| (b.clone() - window.lower.clone()).as_f64();
+
+    let mut out = String::new();
+    for (tag, covered, _) in tags {
+        let spans: Vec<(f64, f64)> = covered
+            .iter()
+            .map(|(l, u)| (offset(l), offset(u)))
+            .collect();
+        let mut bar = String::new();
+        for i in 0..cells {
+            let (lower, upper) = (
+                total * i as f64 / cells as f64,
+                total * (i + 1) as f64 / cells as f64,
+            );
+            let overlap = spans.iter().fold(0.0, |sum, (l, u)| {
+                sum + (u.min(upper) - l.max(lower)).max(0.0)
+            });
+            let (color, glyph) = if overlap >= upper - lower {
+                (GREEN, '█')
+            } else if overlap > 0.0 {
+                (YELLOW, '▒')
+            } else {
+                (RED, '░')
+            };
+            bar.push_str(color);
+            bar.push(glyph);
+        }
+        let ratio = if total > 0.0 {
+            100.0 * spans.iter().fold(0.0, |sum, (l, u)| sum + (u - l)) / total
+        } else {
+            0.0
+        };
+        out.push_str(&format!(
+            "{:<label$} {}{} {:>5.1}%\n",
+            tag,
+            bar,
+            RESET,
+            ratio,
+            label = label
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiv;
+
+    #[test]
+    fn term_works() {
+        let window = tiv(0, 40, &["freedom", "liberty"]);
+        let history = vec![tiv(0, 15, &["freedom"]), tiv(30, 40, &["freedom"])];
+        let bar = |s: &str| {
+            s.chars()
+                .map(|c| match c {
+                    '█' => format!("{}█", GREEN),
+                    '▒' => format!("{}▒", YELLOW),
+                    _ => format!("{}░", RED),
+                })
+                .collect::<String>()
+        };
+
+        assert_eq!(
+            term(&window, &history, 20),
+            format!(
+                "freedom {}{}  62.5%\nliberty {}{}   0.0%\n",
+                bar("█▒░█"),
+                RESET,
+                bar("░░░░"),
+                RESET
+            )
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use crate::{flatten_per_tag, BoundOps, Length, TaggedInterval};
+use chrono::{DateTime, TimeZone};
+use std::fmt::{Display, Write};
+use std::ops::Sub;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HtmlOptions {
+    pub title: String,
+    /// Number of gaps listed per tag; the longest ones are kept.
+    pub max_gaps: usize,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        Self {
+            title: "Coverage report".to_string(),
+            max_gaps: 20,
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Returns the spans each tag of `window` is covered and missing over, sorted
+/// by tag.
+#[allow(clippy::type_complexity)]
+pub(crate) fn per_tag_spans<Bound, M>(
+    window: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+) -> Vec<(String, Vec<(Bound, Bound)>, Vec<(Bound, Bound)>)>
+where
+    Bound: BoundOps,
+    M: Clone + PartialEq,
+{
+    let missing = flatten_per_tag(&window.clone().difference(history.to_vec()));
+    let mut covered = flatten_per_tag(&window.clone().intersection(history));
+    let mut tags: Vec<&String> = window.tags.iter().collect();
+    tags.sort();
+    tags.into_iter()
+        .map(|tag| {
+            (
+                tag.clone(),
+                covered.remove(tag).unwrap_or_default(),
+                missing.get(tag).cloned().unwrap_or_default(),
+            )
+        })
+        .collect()
+}
+
+/// Renders a standalone HTML page with a coverage bar, the covered ratio and
+/// the longest gaps of each tag of `window`.
+pub fn html<Bound, M, D>(
+    window: &TaggedInterval<Bound, M>,
+    history: &[TaggedInterval<Bound, M>],
+    options: &HtmlOptions,
+) -> String
+where
+    Bound: BoundOps + Sub<Output = D>,
+    M: Clone + PartialEq,
+    D: Length,
+{
+    let total = (window.upper.clone() - window.lower.clone()).as_f64();
+    let percent = |from: &Bound, to: &Bound| {
+        if total > 0.0 {
+            100.0 * (to.clone() - from.clone()).as_f64() / total
+        } else {
+            0.0
+        }
+    };
+    let tags = per_tag_spans(window, history);
+    let gap_count: usize = tags.iter().map(|(_, _, gaps)| gaps.len()).sum();
+
+    let mut page = String::new();
+    let title = escape(&options.title);
+    writeln!(
+        page,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">"
+    )
+    .unwrap();
+    writeln!(page, "<title>{}</title>", title).unwrap();
+    writeln!(
+        page,
+        "<style>\
+         body{{font-family:sans-serif}}\
+         .bar{{position:relative;height:1em;background:#e55;width:100%}}\
+         .bar span{{position:absolute;top:0;height:100%;background:#5b5}}\
+         td,th{{padding:0 .5em;text-align:left}}\
+         </style>\n</head>\n<body>"
+    )
+    .unwrap();
+    writeln!(page, "<h1>{}</h1>", title).unwrap();
+    writeln!(
+        page,
+        "<p>{} .. {}: {} tags, {} gaps</p>",
+        escape(&format!("{:?}", window.lower)),
+        escape(&format!("{:?}", window.upper)),
+        tags.len(),
+        gap_count
+    )
+    .unwrap();
+
+    for (tag, covered, gaps) in tags {
+        let ratio = covered
+            .iter()
+            .fold(0.0, |total, (l, u)| total + percent(l, u));
+        writeln!(page, "<h2>{} ({:.1}%)</h2>", escape(&tag), ratio).unwrap();
+        write!(page, "<div class=\"bar\">").unwrap();
+        for (lower, upper) in covered.iter() {
+            write!(
+                page,
+                "<span style=\"left:{:.3}%;width:{:.3}%\"></span>",
+                percent(&window.lower, lower),
+                percent(lower, upper)
+            )
+            .unwrap();
+        }
+        writeln!(page, "</div>").unwrap();
+
+        let mut gaps = gaps;
+        gaps.sort_by(|(l1, u1), (l2, u2)| {
+            (u2.clone() - l2.clone())
+                .cmp(&(u1.clone() - l1.clone()))
+                .then(l1.cmp(l2))
+        });
+        if !gaps.is_empty() {
+            writeln!(page, "<table>\n<tr><th>from</th><th>to</th></tr>").unwrap();
+            for (lower, upper) in gaps.into_iter().take(options.max_gaps) {
+                writeln!(
+                    page,
+                    "<tr><td>{}</td><td>{}</td></tr>",
+                    escape(&format!("{:?}", lower)),
+                    escape(&format!("{:?}", upper))
+                )
+                .unwrap();
+            }
+            writeln!(page, "</table>").unwrap();
+        }
+    }
+
+    writeln!(page, "</body>\n</html>").unwrap();
+    page
+}
+
+/// Renders `intervals`, e.g. a history or a difference result, as a Mermaid
+/// `gantt` chart with one section per tag. Times are in their own zone.
+pub fn gantt<Tz, M>(title: &str, intervals: &[TaggedInterval<DateTime<Tz>, M>]) -> String
+where
+    Tz: TimeZone,
+    Tz::Offset: Display,
+    DateTime<Tz>: BoundOps,
+{
+    let clean = |s: &str| s.replace([':', '#', ';', '\n'], " ");
+    let format = |t: &DateTime<Tz>| t.format("%Y-%m-%dT%H:%M:%S").to_string();
+
+    let mut chart = String::from("gantt\n");
+    writeln!(chart, "    title {}", clean(title)).unwrap();
+    writeln!(chart, "    dateFormat YYYY-MM-DDTHH:mm:ss").unwrap();
+    let mut spans: Vec<_> = flatten_per_tag(intervals).into_iter().collect();
+    spans.sort_by(|x, y| x.0.cmp(&y.0));
+    for (tag, spans) in spans {
+        let tag = clean(&tag);
+        writeln!(chart, "    section {}", tag).unwrap();
+        for (lower, upper) in spans {
+            writeln!(
+                chart,
+                "    {} : {}, {}",
+                tag,
+                format(&lower),
+                format(&upper)
+            )
+            .unwrap();
+        }
+    }
+    chart
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{time, tiv};
+
+    #[test]
+    fn html_works() {
+        let window = tiv(0, 100, &["freedom", "<liberty>"]);
+        let history = vec![tiv(0, 25, &["freedom"]), tiv(50, 100, &["freedom"])];
+        let page = html(&window, &history, &HtmlOptions::default());
+
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("<p>0 .. 100: 2 tags, 2 gaps</p>"));
+        assert!(page.contains("<h2>freedom (75.0%)</h2>"));
+        assert!(page.contains("<span style=\"left:50.000%;width:50.000%\"></span>"));
+        assert!(page.contains("<tr><td>25</td><td>50</td></tr>"));
+        assert!(page.contains("<h2>&lt;liberty&gt; (0.0%)</h2>"));
+        assert!(page.ends_with("</html>\n"));
+    }
+
+    #[test]
+    fn gantt_works() {
+        let history = vec![
+            tiv(
+                time("2077-07-07T09:00:00Z"),
+                time("2077-07-07T12:00:00Z"),
+                &["freedom"],
+            ),
+            tiv(
+                time("2077-07-07T11:00:00Z"),
+                time("2077-07-07T13:00:00Z"),
+                &["freedom", "a:b"],
+            ),
+        ];
+
+        assert_eq!(
+            gantt("Gaps: today", &history),
+            [
+                "gantt",
+                "    title Gaps  today",
+                "    dateFormat YYYY-MM-DDTHH:mm:ss",
+                "    section a b",
+                "    a b : 2077-07-07T11:00:00, 2077-07-07T13:00:00",
+                "    section freedom",
+                "    freedom : 2077-07-07T09:00:00, 2077-07-07T13:00:00",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{BoundOps, TaggedInterval};
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, NaiveTime, TimeZone, Timelike, Weekday};
+
+/// Recurring windows on given weekdays between two times of day, e.g.
+/// Mon–Fri 09:30–16:00. A window whose end is not after its start runs over
+/// midnight into the next day.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schedule {
+    weekdays: Vec<Weekday>,
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl Schedule {
+    pub fn new(weekdays: &[Weekday], start: NaiveTime, end: NaiveTime) -> Self {
+        Self {
+            weekdays: weekdays.to_vec(),
+            start,
+            end,
+        }
+    }
+
+    /// Returns the scheduled windows overlapping `lower..upper`, clipped to
+    /// it. Times of day are taken in the time zone of `lower`; one skipped by
+    /// a transition such as the start of daylight saving time is taken as
+    /// the first instant after the gap.
+    pub fn windows<Tz>(
+        &self,
+        lower: DateTime<Tz>,
+        upper: DateTime<Tz>,
+    ) -> Vec<(DateTime<Tz>, DateTime<Tz>)>
+    where
+        Tz: TimeZone,
+        DateTime<Tz>: BoundOps,
+    {
+        let tz = lower.timezone();
+        let mut windows = vec![];
+        let mut date = lower.date_naive() - Duration::days(1);
+
+        while date <= upper.date_naive() {
+            if self.weekdays.contains(&date.weekday()) {
+                let end_date = if self.end <= self.start {
+                    date + Duration::days(1)
+                } else {
+                    date
+                };
+                let start = resolve(&tz, date.and_time(self.start));
+                let end = resolve(&tz, end_date.and_time(self.end));
+                if let (Some(start), Some(end)) = (start, end) {
+                    let (start, end) = (start.max(lower.clone()), end.min(upper.clone()));
+                    if start < end {
+                        windows.push((start, end));
+                    }
+                }
+            }
+            date += Duration::days(1);
+        }
+        windows
+    }
+
+    /// Splits each of `intervals` into the parts inside the schedule.
+    pub fn restrict<Tz, M>(
+        &self,
+        intervals: &[TaggedInterval<DateTime<Tz>, M>],
+    ) -> Vec<TaggedInterval<DateTime<Tz>, M>>
+    where
+        Tz: TimeZone,
+        DateTime<Tz>: BoundOps,
+        M: Clone,
+    {
+        intervals
+            .iter()
+            .flat_map(|iv| {
+                self.windows(iv.lower.clone(), iv.upper.clone())
+                    .into_iter()
+                    .map(move |(lower, upper)| TaggedInterval {
+                        lower,
+                        upper,
+                        tags: iv.tags.clone(),
+                        meta: iv.meta.clone(),
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Resolves `local` in `tz` to its earliest instant, or to the first instant
+/// after the gap if `local` does not exist there.
+fn resolve<Tz: TimeZone>(tz: &Tz, local: NaiveDateTime) -> Option<DateTime<Tz>> {
+    if let Some(instant) = tz.from_local_datetime(&local).earliest() {
+        return Some(instant);
+    }
+    // Transitions fall on whole minutes, so the first minute that exists
+    // after `local` is where the gap ends.
+    let minute = local.with_second(0)?.with_nanosecond(0)?;
+    (1..=24 * 60).find_map(|m| {
+        tz.from_local_datetime(&(minute + Duration::minutes(m)))
+            .earliest()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{time, tiv};
+
+    #[test]
+    fn restrict_works() {
+        let weekdays = [
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+        ];
+        let market_hours = Schedule::new(
+            &weekdays,
+            NaiveTime::from_hms_opt(9, 30, 0).unwrap(),
+            NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+        );
+        // 2077-07-09 is a Friday.
+        let intervals = vec![tiv(
+            time("2077-07-09T12:00:00Z"),
+            time("2077-07-12T10:00:00Z"),
+            &["freedom"],
+        )];
+
+        assert_eq!(
+            market_hours.restrict(&intervals),
+            vec![
+                tiv(
+                    time("2077-07-09T12:00:00Z"),
+                    time("2077-07-09T16:00:00Z"),
+                    &["freedom"]
+                ),
+                tiv(
+                    time("2077-07-12T09:30:00Z"),
+                    time("2077-07-12T10:00:00Z"),
+                    &["freedom"]
+                ),
+            ]
+        );
+
+        let overnight = Schedule::new(
+            &[Weekday::Fri],
+            NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
+        );
+        assert_eq!(
+            overnight.restrict(&intervals),
+            vec![tiv(
+                time("2077-07-09T22:00:00Z"),
+                time("2077-07-10T02:00:00Z"),
+                &["freedom"]
+            )]
+        );
+    }
+
+    #[test]
+    fn windows_in_spring_forward_gap() {
+        use chrono_tz::America::New_York;
+
+        // On 2077-03-14 New York clocks jump from 02:00 to 03:00.
+        let nightly = Schedule::new(
+            &[Weekday::Sun],
+            NaiveTime::from_hms_opt(2, 30, 0).unwrap(),
+            NaiveTime::from_hms_opt(4, 0, 0).unwrap(),
+        );
+        let lower = New_York.with_ymd_and_hms(2077, 3, 14, 0, 0, 0).unwrap();
+        let upper = New_York.with_ymd_and_hms(2077, 3, 15, 0, 0, 0).unwrap();
+
+        assert_eq!(
+            nightly.windows(lower, upper),
+            vec![(
+                New_York.with_ymd_and_hms(2077, 3, 14, 3, 0, 0).unwrap(),
+                New_York.with_ymd_and_hms(2077, 3, 14, 4, 0, 0).unwrap(),
+            )]
+        );
+    }
+}
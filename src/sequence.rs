@@ -0,0 +1,68 @@
+//! Helpers for sequence numbers, such as WAL segment or log sequence
+//! numbers. Sequences are discrete, so ranges here are inclusive: `3..=5`
+//! holds 3, 4 and 5. Any integer type works as a bound of `TaggedInterval`
+//! directly, with `lower..upper` holding `lower` up to `upper - 1`; these
+//! helpers do that conversion, without overflowing at `u64::MAX`.
+
+use crate::TaggedInterval;
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+fn half_open(range: &RangeInclusive<u64>, tags: &HashSet<String>) -> TaggedInterval<u128> {
+    TaggedInterval::new(
+        *range.start() as u128,
+        *range.end() as u128 + 1,
+        tags.clone(),
+    )
+}
+
+/// Returns the sequence numbers of `window` not in any range of `history`.
+pub fn missing_sequences(
+    history: &[RangeInclusive<u64>],
+    window: RangeInclusive<u64>,
+) -> Vec<RangeInclusive<u64>> {
+    if window.is_empty() {
+        return vec![];
+    }
+    let tags: HashSet<String> = ["sequence".to_string()].iter().cloned().collect();
+    let history = history
+        .iter()
+        .filter(|r| !r.is_empty())
+        .map(|r| half_open(r, &tags))
+        .collect();
+    half_open(&window, &tags)
+        .difference(history)
+        .into_iter()
+        .map(|iv| iv.lower as u64..=(iv.upper - 1) as u64)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_sequences_works() {
+        let table = vec![
+            (vec![], 0..=9, vec![0..=9]),
+            (vec![0..=3, 5..=5, 6..=8], 0..=9, vec![4..=4, 9..=9]),
+            (vec![0..=9], 3..=5, vec![]),
+            (vec![RangeInclusive::new(7, 3)], 3..=5, vec![3..=5]),
+            (
+                vec![10..=u64::MAX - 1],
+                0..=u64::MAX,
+                vec![0..=9, u64::MAX..=u64::MAX],
+            ),
+        ];
+
+        for (history, window, expected) in table {
+            assert_eq!(
+                missing_sequences(&history, window.clone()),
+                expected,
+                "{:?} {:?}",
+                history,
+                window
+            );
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use crate::timeline::Timeline;
+use crate::{BoundOps, Snap, TaggedInterval};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::Add;
+
+/// Approximate coverage for very many tags: one Bloom filter of covered
+/// tags per bucket of a fixed resolution. Memory is `bits` per bucket
+/// regardless of the number of tags, plus the exact coverage of at most
+/// `max_partial` pairs of a bucket and a tag that only partly covers it so
+/// far. A tag reported as covered may be a false positive. One reported as
+/// not covered is certainly not, unless it covers the bucket only through
+/// parts recorded after that limit was reached.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sketch<Bound, D>
+where
+    Bound: BoundOps,
+{
+    resolution: D,
+    bits: usize,
+    hashes: u32,
+    buckets: BTreeMap<Bound, Vec<u64>>,
+    /// Coverage of each tag in buckets it does not yet cover entirely.
+    partial: BTreeMap<Bound, HashMap<String, Timeline<Bound>>>,
+    /// The number of timelines in `partial`.
+    partial_len: usize,
+    max_partial: usize,
+}
+
+impl<Bound, D> Sketch<Bound, D>
+where
+    Bound: BoundOps + Snap<D> + Add<D, Output = Bound>,
+    D: Copy,
+{
+    /// Panics if `bits` or `hashes` is zero.
+    pub fn new(resolution: D, bits: usize, hashes: u32, max_partial: usize) -> Self {
+        assert!(bits > 0 && hashes > 0, "bits and hashes must be positive");
+        Self {
+            resolution,
+            bits,
+            hashes,
+            buckets: BTreeMap::new(),
+            partial: BTreeMap::new(),
+            partial_len: 0,
+            max_partial,
+        }
+    }
+
+    fn positions(&self, tag: &str) -> impl Iterator<Item = usize> {
+        let mut hasher = DefaultHasher::new();
+        tag.hash(&mut hasher);
+        let hash = hasher.finish();
+        let (h1, h2) = (hash & 0xffff_ffff, (hash >> 32) | 1);
+        let bits = self.bits as u64;
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bits) as usize)
+    }
+
+    fn mark(&mut self, bucket: &Bound, tag: &str) {
+        let positions: Vec<usize> = self.positions(tag).collect();
+        let words = self.bits.div_ceil(64);
+        let filter = self
+            .buckets
+            .entry(bucket.clone())
+            .or_insert_with(|| vec![0; words]);
+        for p in positions {
+            filter[p / 64] |= 1 << (p % 64);
+        }
+    }
+
+    /// Marks the tags of `interval` as covered in each bucket it covers.
+    /// Parts of buckets are kept exactly until the tag covers the whole
+    /// bucket, e.g. through abutting intervals, as long as fewer than
+    /// `max_partial` are kept; otherwise they are dropped.
+    pub fn record<M>(&mut self, interval: &TaggedInterval<Bound, M>) {
+        let mut bucket = interval.lower.snap_down(self.resolution);
+        while bucket < interval.upper {
+            let next = bucket.clone() + self.resolution;
+            let lower = interval.lower.clone().max(bucket.clone());
+            let upper = interval.upper.clone().min(next.clone());
+            for tag in interval.tags.iter() {
+                if lower == bucket && upper == next {
+                    self.mark(&bucket, tag);
+                    continue;
+                }
+                let tracked = self
+                    .partial
+                    .get(&bucket)
+                    .is_some_and(|tags| tags.contains_key(tag));
+                if !tracked {
+                    if self.partial_len >= self.max_partial {
+                        continue;
+                    }
+                    self.partial_len += 1;
+                }
+                let tags = self.partial.entry(bucket.clone()).or_default();
+                let timeline = tags.entry(tag.clone()).or_default();
+                *timeline =
+                    timeline.union(&Timeline::from_spans(&[(lower.clone(), upper.clone())]));
+                if timeline.spans() == [(bucket.clone(), next.clone())] {
+                    tags.remove(tag);
+                    if tags.is_empty() {
+                        self.partial.remove(&bucket);
+                    }
+                    self.partial_len -= 1;
+                    self.mark(&bucket, tag);
+                }
+            }
+            bucket = next;
+        }
+    }
+
+    /// Returns whether `tag` is probably covered in the bucket holding `at`.
+    pub fn contains(&self, tag: &str, at: &Bound) -> bool {
+        match self.buckets.get(&at.snap_down(self.resolution)) {
+            Some(filter) => self
+                .positions(tag)
+                .all(|p| filter[p / 64] >> (p % 64) & 1 == 1),
+            None => false,
+        }
+    }
+
+    /// Returns the buckets overlapping `window` with the tags of `window`
+    /// certainly missing there, one interval per bucket.
+    pub fn missing<M>(&self, window: &TaggedInterval<Bound, M>) -> Vec<TaggedInterval<Bound>> {
+        let mut result = vec![];
+        let mut bucket = window.lower.snap_down(self.resolution);
+        while bucket < window.upper {
+            let missing: HashSet<String> = window
+                .tags
+                .iter()
+                .filter(|tag| !self.contains(tag, &bucket))
+                .cloned()
+                .collect();
+            let next = bucket.clone() + self.resolution;
+            if !missing.is_empty() {
+                result.push(TaggedInterval::new(bucket, next.clone(), missing));
+            }
+            bucket = next;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiv;
+
+    #[test]
+    fn sketch_works() {
+        let mut sketch = Sketch::new(10, 1024, 3, 16);
+        sketch.record(&tiv(0, 35, &["freedom", "liberty"]));
+        sketch.record(&tiv(45, 60, &["freedom"]));
+
+        assert!(sketch.contains("freedom", &0));
+        assert!(sketch.contains("liberty", &29));
+        assert!(!sketch.contains("liberty", &30));
+        assert!(!sketch.contains("freedom", &45));
+        assert!(sketch.contains("freedom", &50));
+        assert_eq!(
+            sketch.missing(&tiv(5, 65, &["freedom", "liberty"])),
+            vec![
+                tiv(30, 40, &["freedom", "liberty"]),
+                tiv(40, 50, &["freedom", "liberty"]),
+                tiv(50, 60, &["liberty"]),
+                tiv(60, 70, &["freedom", "liberty"]),
+            ]
+        );
+
+        // Many tags fit in few bits, with no false negatives.
+        let mut sketch = Sketch::new(10, 4096, 4, 16);
+        let many: Vec<String> = (0..500).map(|i| format!("tag-{}", i)).collect();
+        for tag in many.iter() {
+            sketch.record(&tiv(0, 10, &[tag]));
+        }
+        assert!(many.iter().all(|tag| sketch.contains(tag, &5)));
+        assert!(!sketch.contains("tag-0", &15));
+    }
+
+    #[test]
+    fn sketch_joins_abutting_entries() {
+        let mut sketch = Sketch::new(10, 1024, 3, 16);
+        sketch.record(&tiv(0, 5, &["freedom"]));
+        assert!(!sketch.contains("freedom", &0));
+        sketch.record(&tiv(5, 10, &["freedom", "liberty"]));
+        sketch.record(&tiv(12, 15, &["freedom"]));
+        sketch.record(&tiv(15, 22, &["freedom"]));
+        sketch.record(&tiv(11, 13, &["freedom"]));
+
+        assert!(sketch.contains("freedom", &0));
+        assert!(!sketch.contains("liberty", &0));
+        assert!(!sketch.contains("freedom", &10));
+        assert_eq!(
+            sketch.missing(&tiv(0, 30, &["freedom"])),
+            vec![tiv(10, 20, &["freedom"]), tiv(20, 30, &["freedom"])]
+        );
+
+        sketch.record(&tiv(10, 11, &["freedom"]));
+        assert!(sketch.contains("freedom", &10));
+        assert!(!sketch.partial.contains_key(&10));
+        assert_eq!(sketch.partial_len, 2);
+    }
+
+    #[test]
+    fn sketch_bounds_partial_buckets() {
+        let mut sketch = Sketch::new(10, 1024, 3, 2);
+        sketch.record(&tiv(0, 5, &["freedom", "liberty"]));
+        sketch.record(&tiv(10, 15, &["freedom"]));
+        assert_eq!(sketch.partial_len, 2);
+        assert!(!sketch.partial.contains_key(&10));
+
+        // The dropped part leaves its bucket reported as missing.
+        sketch.record(&tiv(15, 20, &["freedom"]));
+        assert!(!sketch.contains("freedom", &10));
+
+        // Completing a kept part frees room for another.
+        sketch.record(&tiv(5, 10, &["freedom"]));
+        assert!(sketch.contains("freedom", &0));
+        sketch.record(&tiv(20, 25, &["freedom"]));
+        sketch.record(&tiv(25, 30, &["freedom"]));
+        assert!(sketch.contains("freedom", &20));
+        assert_eq!(sketch.partial_len, 1);
+    }
+}
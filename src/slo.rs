@@ -0,0 +1,112 @@
+use crate::{flatten_per_tag, BoundOps, Length, TaggedInterval};
+use std::collections::HashSet;
+use std::ops::Sub;
+
+/// A completeness target: `tag` must be covered at least `min_ratio` of the
+/// trailing `trailing` before the evaluation time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Target<D> {
+    pub tag: String,
+    pub min_ratio: f64,
+    pub trailing: D,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Evaluation<Bound> {
+    pub tag: String,
+    pub ratio: f64,
+    pub passed: bool,
+    /// The missing ranges of the trailing window.
+    pub gaps: Vec<(Bound, Bound)>,
+}
+
+/// Evaluates each of `targets` against `history` at `now`.
+pub fn evaluate<Bound, M, D>(
+    targets: &[Target<D>],
+    history: &[TaggedInterval<Bound, M>],
+    now: Bound,
+) -> Vec<Evaluation<Bound>>
+where
+    Bound: BoundOps + Sub<Output = D> + Sub<D, Output = Bound>,
+    M: Clone + PartialEq,
+    D: Length,
+{
+    targets
+        .iter()
+        .map(|target| {
+            let tags: HashSet<String> = [target.tag.clone()].iter().cloned().collect();
+            let window = TaggedInterval {
+                lower: now.clone() - target.trailing,
+                upper: now.clone(),
+                tags,
+                meta: None,
+            };
+            let gaps = flatten_per_tag(&window.difference(history.to_vec()))
+                .remove(&target.tag)
+                .unwrap_or_default();
+            let total = target.trailing.as_f64();
+            let missing = gaps
+                .iter()
+                .fold(0.0, |sum, (l, u)| sum + (u.clone() - l.clone()).as_f64());
+            let ratio = if total > 0.0 {
+                (total - missing) / total
+            } else {
+                1.0
+            };
+            Evaluation {
+                tag: target.tag.clone(),
+                ratio,
+                passed: ratio >= target.min_ratio,
+                gaps,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiv;
+
+    #[test]
+    fn evaluate_works() {
+        let history = vec![
+            tiv(0, 95, &["freedom", "liberty"]),
+            tiv(96, 200, &["freedom"]),
+        ];
+        let target = |tag: &str, min_ratio: f64| Target {
+            tag: tag.to_string(),
+            min_ratio,
+            trailing: 100,
+        };
+        let targets = vec![
+            target("freedom", 0.99),
+            target("freedom", 0.995),
+            target("liberty", 0.5),
+        ];
+
+        assert_eq!(
+            evaluate(&targets, &history, 150),
+            vec![
+                Evaluation {
+                    tag: "freedom".to_string(),
+                    ratio: 0.99,
+                    passed: true,
+                    gaps: vec![(95, 96)],
+                },
+                Evaluation {
+                    tag: "freedom".to_string(),
+                    ratio: 0.99,
+                    passed: false,
+                    gaps: vec![(95, 96)],
+                },
+                Evaluation {
+                    tag: "liberty".to_string(),
+                    ratio: 0.45,
+                    passed: false,
+                    gaps: vec![(95, 150)],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::{BTreeSet, HashSet};
+
+/// A set of interned tag IDs, as kept by the sweep for the covered tags.
+pub trait TagSet: Clone + Default + PartialEq {
+    /// The number of distinct IDs the set can hold, if bounded.
+    const CAPACITY: Option<usize> = None;
+
+    fn insert(&mut self, id: usize);
+    fn remove(&mut self, id: usize);
+
+    /// Returns the IDs in `self` that are not in `other`.
+    fn difference(&self, other: &Self) -> Vec<usize>;
+}
+
+impl TagSet for HashSet<usize> {
+    fn insert(&mut self, id: usize) {
+        HashSet::insert(self, id);
+    }
+
+    fn remove(&mut self, id: usize) {
+        HashSet::remove(self, &id);
+    }
+
+    fn difference(&self, other: &Self) -> Vec<usize> {
+        HashSet::difference(self, other).cloned().collect()
+    }
+}
+
+impl TagSet for BTreeSet<usize> {
+    fn insert(&mut self, id: usize) {
+        BTreeSet::insert(self, id);
+    }
+
+    fn remove(&mut self, id: usize) {
+        BTreeSet::remove(self, &id);
+    }
+
+    fn difference(&self, other: &Self) -> Vec<usize> {
+        BTreeSet::difference(self, other).cloned().collect()
+    }
+}
+
+macro_rules! impl_bitmask {
+    ($($t:ty),*) => {
+        $(impl TagSet for $t {
+            const CAPACITY: Option<usize> = Some(<$t>::BITS as usize);
+
+            fn insert(&mut self, id: usize) {
+                *self |= 1 << id;
+            }
+
+            fn remove(&mut self, id: usize) {
+                *self &= !(1 << id);
+            }
+
+            fn difference(&self, other: &Self) -> Vec<usize> {
+                let mut rest = *self & !*other;
+                let mut ids = vec![];
+                while rest != 0 {
+                    ids.push(rest.trailing_zeros() as usize);
+                    rest &= rest - 1;
+                }
+                ids
+            }
+        })*
+    };
+}
+
+impl_bitmask!(u64, u128);
+
+/// IDs kept in a sorted vector; compact and fast to compare for few tags.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Interned(Vec<u32>);
+
+impl TagSet for Interned {
+    const CAPACITY: Option<usize> = Some(u32::MAX as usize);
+
+    fn insert(&mut self, id: usize) {
+        if let Err(i) = self.0.binary_search(&(id as u32)) {
+            self.0.insert(i, id as u32);
+        }
+    }
+
+    fn remove(&mut self, id: usize) {
+        if let Ok(i) = self.0.binary_search(&(id as u32)) {
+            self.0.remove(i);
+        }
+    }
+
+    fn difference(&self, other: &Self) -> Vec<usize> {
+        self.0
+            .iter()
+            .filter(|id| other.0.binary_search(id).is_err())
+            .map(|id| *id as usize)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check<S: TagSet>() {
+        let mut a = S::default();
+        let mut b = S::default();
+        for id in [1, 5, 63] {
+            a.insert(id);
+        }
+        b.insert(5);
+        b.insert(7);
+        b.remove(7);
+
+        let mut diff = a.difference(&b);
+        diff.sort();
+        assert_eq!(diff, vec![1, 63]);
+        assert!(b.difference(&a).is_empty());
+        a.remove(1);
+        a.remove(63);
+        assert!(a == b);
+    }
+
+    #[test]
+    fn tag_sets_work() {
+        check::<HashSet<usize>>();
+        check::<BTreeSet<usize>>();
+        check::<u64>();
+        check::<u128>();
+        check::<Interned>();
+    }
+}
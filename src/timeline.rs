@@ -0,0 +1,177 @@
+use crate::{flatten_per_tag, BoundOps, TaggedInterval};
+use std::collections::{HashMap, HashSet};
+
+/// Coverage of a single tag as alternating covered and uncovered runs.
+///
+/// `changes` holds the bounds where coverage flips, starting with a flip to
+/// covered, so it always has an even length.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timeline<Bound>
+where
+    Bound: BoundOps,
+{
+    changes: Vec<Bound>,
+}
+
+impl<Bound> Default for Timeline<Bound>
+where
+    Bound: BoundOps,
+{
+    fn default() -> Self {
+        Self { changes: vec![] }
+    }
+}
+
+impl<Bound> Timeline<Bound>
+where
+    Bound: BoundOps,
+{
+    pub fn from_spans(spans: &[(Bound, Bound)]) -> Self {
+        let mut spans: Vec<(Bound, Bound)> = spans.iter().filter(|(l, u)| l < u).cloned().collect();
+        spans.sort();
+
+        let mut changes: Vec<Bound> = vec![];
+        for (lower, upper) in spans {
+            match changes.last_mut() {
+                Some(last) if lower <= *last => *last = last.clone().max(upper),
+                _ => changes.extend([lower, upper]),
+            }
+        }
+        Self { changes }
+    }
+
+    pub fn per_tag<M>(history: &[TaggedInterval<Bound, M>]) -> HashMap<String, Self> {
+        flatten_per_tag(history)
+            .into_iter()
+            .map(|(tag, spans)| {
+                (
+                    tag,
+                    Self {
+                        changes: spans.into_iter().flat_map(|(l, u)| [l, u]).collect(),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    pub fn changes(&self) -> &[Bound] {
+        &self.changes
+    }
+
+    pub fn spans(&self) -> Vec<(Bound, Bound)> {
+        self.changes
+            .chunks(2)
+            .map(|c| (c[0].clone(), c[1].clone()))
+            .collect()
+    }
+
+    /// Returns the runs between the first and the last change as
+    /// `(lower, upper, covered)`.
+    pub fn runs(&self) -> Vec<(Bound, Bound, bool)> {
+        self.changes
+            .windows(2)
+            .enumerate()
+            .map(|(i, w)| (w[0].clone(), w[1].clone(), i % 2 == 0))
+            .collect()
+    }
+
+    pub fn is_covered(&self, point: Bound) -> bool {
+        self.changes.partition_point(|b| *b <= point) % 2 == 1
+    }
+
+    pub fn to_intervals(&self, tag: &str) -> Vec<TaggedInterval<Bound>> {
+        let tags: HashSet<String> = [tag.to_string()].iter().cloned().collect();
+        self.spans()
+            .into_iter()
+            .map(|(l, u)| TaggedInterval::new(l, u, tags.clone()))
+            .collect()
+    }
+
+    /// Combines two timelines run by run with `op`, which must map
+    /// `(false, false)` to `false`.
+    pub fn combine<F>(&self, other: &Self, op: F) -> Self
+    where
+        F: Fn(bool, bool) -> bool,
+    {
+        let (mut i, mut j) = (0, 0);
+        let mut covered = false;
+        let mut changes = vec![];
+
+        while i < self.changes.len() || j < other.changes.len() {
+            let point = match (self.changes.get(i), other.changes.get(j)) {
+                (Some(a), Some(b)) => a.min(b).clone(),
+                (Some(a), None) => a.clone(),
+                (None, Some(b)) => b.clone(),
+                (None, None) => unreachable!(),
+            };
+            while self.changes.get(i) == Some(&point) {
+                i += 1;
+            }
+            while other.changes.get(j) == Some(&point) {
+                j += 1;
+            }
+            let next = op(i % 2 == 1, j % 2 == 1);
+            if next != covered {
+                changes.push(point);
+                covered = next;
+            }
+        }
+
+        Self { changes }
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a || b)
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a && b)
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a && !b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiv;
+
+    #[test]
+    fn timeline_works() {
+        let history = vec![
+            tiv(0, 10, &["freedom", "liberty"]),
+            tiv(5, 20, &["freedom"]),
+            tiv(30, 40, &["freedom"]),
+            tiv(15, 35, &["liberty"]),
+        ];
+        let timelines = Timeline::per_tag(&history);
+        let freedom = &timelines["freedom"];
+        let liberty = &timelines["liberty"];
+
+        assert_eq!(freedom.changes(), &[0, 20, 30, 40]);
+        assert_eq!(
+            freedom.runs(),
+            vec![(0, 20, true), (20, 30, false), (30, 40, true)]
+        );
+        assert!(freedom.is_covered(0));
+        assert!(!freedom.is_covered(20));
+        assert!(!freedom.is_covered(40));
+        assert_eq!(
+            freedom.to_intervals("freedom"),
+            vec![tiv(0, 20, &["freedom"]), tiv(30, 40, &["freedom"])]
+        );
+        assert_eq!(Timeline::from_spans(&freedom.spans()), *freedom);
+
+        assert_eq!(freedom.union(liberty).spans(), vec![(0, 40)]);
+        assert_eq!(
+            freedom.intersection(liberty).spans(),
+            vec![(0, 10), (15, 20), (30, 35)]
+        );
+        assert_eq!(
+            freedom.difference(liberty).spans(),
+            vec![(10, 15), (35, 40)]
+        );
+    }
+}
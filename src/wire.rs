@@ -0,0 +1,323 @@
+//! A compact interchange format for per-tag coverage over an integer grid.
+//!
+//! All integers are LEB128 varints; signed ones are zigzag-encoded first.
+//!
+//! ```text
+//! "TIV" version=1
+//! origin:i step:u cells:u
+//! tag_count:u
+//! per tag, in byte order of the names:
+//!   name_len:u name:utf8
+//!   run_count:u
+//!   per run: skip:u length:u
+//! ```
+//!
+//! Runs are covered cells; `skip` counts the uncovered cells since the end of
+//! the previous run (or since the origin), so cell `c` is the range
+//! `origin + c * step..origin + (c + 1) * step`.
+
+use crate::{flatten_per_tag, Snap, TaggedInterval};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{self, Display};
+
+const MAGIC: &[u8] = b"TIV";
+const VERSION: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Grid {
+    pub origin: i64,
+    pub step: i64,
+    pub cells: u64,
+}
+
+impl Grid {
+    /// Returns the lower bound of `cell`, or `None` if it does not fit in an
+    /// `i64`.
+    fn bound(&self, cell: u64) -> Option<i64> {
+        i64::try_from(cell)
+            .ok()?
+            .checked_mul(self.step)?
+            .checked_add(self.origin)
+    }
+
+    /// Returns the cells fully inside `lower..upper`, clipped to the grid.
+    fn cells_within(&self, lower: i64, upper: i64) -> (u64, u64) {
+        // Offsets from the origin may not fit in an `i64`, but always do in
+        // an `i128`.
+        let step = i128::from(self.step);
+        let cell = |offset: i128| u64::try_from(offset.max(0) / step).unwrap_or(u64::MAX);
+        let first = cell((i128::from(lower) - i128::from(self.origin)).snap_up(step));
+        let last = cell((i128::from(upper) - i128::from(self.origin)).snap_down(step));
+        (first.min(self.cells), last.min(self.cells))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+    InvalidUtf8,
+    /// The grid has a non-positive step, its bounds overflow an `i64`, or a
+    /// run exceeds its cells.
+    InvalidGrid,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::BadMagic => write!(f, "not a coverage snapshot"),
+            DecodeError::UnsupportedVersion(v) => write!(f, "unsupported version {}", v),
+            DecodeError::Truncated => write!(f, "unexpected end of input"),
+            DecodeError::InvalidUtf8 => write!(f, "tag is not valid UTF-8"),
+            DecodeError::InvalidGrid => write!(f, "runs do not fit the grid"),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+fn put_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn put_signed(out: &mut Vec<u8>, n: i64) {
+    put_varint(out, ((n << 1) ^ (n >> 63)) as u64);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if self.bytes.len() < n {
+            return Err(DecodeError::Truncated);
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            n |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(DecodeError::InvalidGrid)
+    }
+
+    fn signed(&mut self) -> Result<i64, DecodeError> {
+        let n = self.varint()?;
+        Ok((n >> 1) as i64 ^ -((n & 1) as i64))
+    }
+}
+
+/// Encodes the coverage of `history` on `grid`. Entries are snapped inward,
+/// so a cell counts as covered only if some entry covers all of it.
+///
+/// Panics if the step of `grid` is not positive or if its last bound does
+/// not fit in an `i64`.
+pub fn encode<M>(grid: Grid, history: &[TaggedInterval<i64, M>]) -> Vec<u8> {
+    assert!(grid.step > 0, "step must be positive");
+    assert!(grid.bound(grid.cells).is_some(), "grid overflows i64");
+
+    let mut out = MAGIC.to_vec();
+    out.push(VERSION);
+    put_signed(&mut out, grid.origin);
+    put_varint(&mut out, grid.step as u64);
+    put_varint(&mut out, grid.cells);
+
+    let mut spans: Vec<_> = flatten_per_tag(history).into_iter().collect();
+    spans.sort();
+    put_varint(&mut out, spans.len() as u64);
+    for (tag, spans) in spans {
+        put_varint(&mut out, tag.len() as u64);
+        out.extend(tag.as_bytes());
+
+        let mut runs: Vec<(u64, u64)> = vec![];
+        for (lower, upper) in spans {
+            let (first, last) = grid.cells_within(lower, upper);
+            if first >= last {
+                continue;
+            }
+            match runs.last_mut() {
+                Some(run) if run.1 == first => run.1 = last,
+                _ => runs.push((first, last)),
+            }
+        }
+        put_varint(&mut out, runs.len() as u64);
+        let mut end = 0;
+        for (first, last) in runs {
+            put_varint(&mut out, first - end);
+            put_varint(&mut out, last - first);
+            end = last;
+        }
+    }
+    out
+}
+
+/// Decodes a snapshot made by `encode` into its grid and one interval per
+/// run, ordered by tag and then by lower bound.
+pub fn decode(bytes: &[u8]) -> Result<(Grid, Vec<TaggedInterval<i64>>), DecodeError> {
+    let mut reader = Reader { bytes };
+    if reader
+        .take(MAGIC.len())
+        .map_err(|_| DecodeError::BadMagic)?
+        != MAGIC
+    {
+        return Err(DecodeError::BadMagic);
+    }
+    let version = reader.take(1)?[0];
+    if version != VERSION {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+    let grid = Grid {
+        origin: reader.signed()?,
+        step: i64::try_from(reader.varint()?).map_err(|_| DecodeError::InvalidGrid)?,
+        cells: reader.varint()?,
+    };
+    if grid.step <= 0 || grid.bound(grid.cells).is_none() {
+        return Err(DecodeError::InvalidGrid);
+    }
+
+    let mut result = vec![];
+    for _ in 0..reader.varint()? {
+        let len = reader.varint()? as usize;
+        let tag = std::str::from_utf8(reader.take(len)?).map_err(|_| DecodeError::InvalidUtf8)?;
+        let tags: HashSet<String> = [tag.to_string()].iter().cloned().collect();
+
+        let mut end = 0u64;
+        for _ in 0..reader.varint()? {
+            let first = end
+                .checked_add(reader.varint()?)
+                .ok_or(DecodeError::InvalidGrid)?;
+            end = first
+                .checked_add(reader.varint()?)
+                .ok_or(DecodeError::InvalidGrid)?;
+            if end > grid.cells {
+                return Err(DecodeError::InvalidGrid);
+            }
+            let lower = grid.bound(first).ok_or(DecodeError::InvalidGrid)?;
+            let upper = grid.bound(end).ok_or(DecodeError::InvalidGrid)?;
+            result.push(TaggedInterval::new(lower, upper, tags.clone()));
+        }
+    }
+    Ok((grid, result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiv;
+
+    #[test]
+    fn wire_round_trips() {
+        let grid = Grid {
+            origin: -10,
+            step: 5,
+            cells: 10,
+        };
+        let history = vec![
+            tiv(-10, 5, &["freedom", "liberty"]),
+            tiv(5, 12, &["freedom"]),
+            tiv(22, 100, &["liberty"]),
+        ];
+        let bytes = encode(grid, &history);
+
+        assert_eq!(
+            decode(&bytes),
+            Ok((
+                grid,
+                vec![
+                    tiv(-10, 10, &["freedom"]),
+                    tiv(-10, 5, &["liberty"]),
+                    tiv(25, 40, &["liberty"]),
+                ]
+            ))
+        );
+        assert_eq!(decode(b"JSON"), Err(DecodeError::BadMagic));
+        assert_eq!(
+            decode(&bytes[..bytes.len() - 1]),
+            Err(DecodeError::Truncated)
+        );
+    }
+
+    #[test]
+    fn decode_rejects_overflowing_grids() {
+        let header = |origin: i64, step: u64, cells: u64| {
+            let mut bytes = MAGIC.to_vec();
+            bytes.push(VERSION);
+            put_signed(&mut bytes, origin);
+            put_varint(&mut bytes, step);
+            put_varint(&mut bytes, cells);
+            bytes
+        };
+        let with_run = |mut bytes: Vec<u8>, skip: u64, length: u64| {
+            put_varint(&mut bytes, 1);
+            put_varint(&mut bytes, 1);
+            bytes.push(b'a');
+            put_varint(&mut bytes, 1);
+            put_varint(&mut bytes, skip);
+            put_varint(&mut bytes, length);
+            bytes
+        };
+
+        // The step does not fit in an `i64`.
+        assert_eq!(
+            decode(&header(0, u64::MAX, 1)),
+            Err(DecodeError::InvalidGrid)
+        );
+        // The last bound overflows.
+        assert_eq!(
+            decode(&header(0, 1 << 40, 1 << 40)),
+            Err(DecodeError::InvalidGrid)
+        );
+        assert_eq!(
+            decode(&header(i64::MAX - 10, 5, 3)),
+            Err(DecodeError::InvalidGrid)
+        );
+        // A run past the cells.
+        assert_eq!(
+            decode(&with_run(header(0, 1, 10), u64::MAX, 2)),
+            Err(DecodeError::InvalidGrid)
+        );
+        // The largest grid that fits decodes.
+        assert_eq!(
+            decode(&with_run(header(i64::MAX - 10, 5, 2), 1, 1)),
+            Ok((
+                Grid {
+                    origin: i64::MAX - 10,
+                    step: 5,
+                    cells: 2,
+                },
+                vec![tiv(i64::MAX - 5, i64::MAX, &["a"])]
+            ))
+        );
+    }
+
+    #[test]
+    fn encode_handles_extreme_bounds() {
+        let grid = Grid {
+            origin: i64::MAX - 100,
+            step: 10,
+            cells: 10,
+        };
+        let history = vec![tiv(i64::MIN, i64::MAX, &["freedom"])];
+
+        assert_eq!(
+            decode(&encode(grid, &history)),
+            Ok((grid, vec![tiv(i64::MAX - 100, i64::MAX, &["freedom"])]))
+        );
+    }
+}
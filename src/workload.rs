@@ -0,0 +1,105 @@
+use crate::{Lcg, TaggedInterval};
+
+/// Parameters of a synthetic history over `0..span`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Workload {
+    pub entries: usize,
+    pub span: i64,
+    /// Average number of entries covering a point.
+    pub overlap: f64,
+    /// Number of distinct tags, named `tag0`, `tag1`, ...
+    pub tags: usize,
+    /// Number of tags on each entry.
+    pub tags_per_entry: usize,
+    pub seed: u64,
+}
+
+impl Default for Workload {
+    fn default() -> Self {
+        Self {
+            entries: 1000,
+            span: 1_000_000,
+            overlap: 2.0,
+            tags: 8,
+            tags_per_entry: 2,
+            seed: 1,
+        }
+    }
+}
+
+fn tag_names(n: usize) -> Vec<String> {
+    (0..n).map(|i| format!("tag{}", i)).collect()
+}
+
+impl Workload {
+    /// Returns the window `0..span` asking for every tag.
+    pub fn specified(&self) -> TaggedInterval<i64> {
+        TaggedInterval::new(0, self.span, tag_names(self.tags).into_iter().collect())
+    }
+
+    /// Returns `entries` random entries whose lengths average to
+    /// `overlap * span / entries`. With no tags, the entries have none.
+    pub fn history(&self) -> Vec<TaggedInterval<i64>> {
+        let names = tag_names(self.tags);
+        let mut rng = Lcg(self.seed);
+        let mean = (self.overlap * self.span as f64 / self.entries.max(1) as f64) as u64;
+
+        (0..self.entries)
+            .map(|_| {
+                let lower = rng.next(self.span as u64) as i64;
+                let upper = lower + 1 + rng.next(2 * mean) as i64;
+                let tags = (0..self.tags_per_entry)
+                    .filter(|_| !names.is_empty())
+                    .map(|_| names[rng.next(self.tags as u64) as usize].clone())
+                    .collect();
+                TaggedInterval::new(lower, upper.min(self.span), tags)
+            })
+            .collect()
+    }
+
+    /// Returns `entries` short entries alternating between the tags and
+    /// touching each other, the worst case for output fragmentation.
+    pub fn zigzag(&self) -> Vec<TaggedInterval<i64>> {
+        let names = tag_names(self.tags.max(1));
+        let step = (self.span / self.entries.max(1) as i64).max(1);
+        (0..self.entries)
+            .map(|i| {
+                let lower = i as i64 * step;
+                let tags = [names[i % names.len()].clone()].iter().cloned().collect();
+                TaggedInterval::new(lower, lower + step, tags)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workload_works() {
+        let workload = Workload {
+            entries: 100,
+            span: 1000,
+            ..Workload::default()
+        };
+        let history = workload.history();
+
+        assert_eq!(history.len(), 100);
+        assert_eq!(history, workload.history());
+        assert!(history
+            .iter()
+            .all(|iv| 0 <= iv.lower && iv.lower < iv.upper && iv.upper <= 1000));
+        assert_eq!(workload.zigzag().len(), 100);
+        assert!(!workload
+            .specified()
+            .difference(workload.zigzag())
+            .is_empty());
+
+        let untagged = Workload {
+            tags: 0,
+            ..workload
+        };
+        assert!(untagged.history().iter().all(|iv| iv.tags.is_empty()));
+    }
+}